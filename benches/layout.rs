@@ -0,0 +1,88 @@
+//! Times solving deep and wide trees. Run with `cargo bench --no-default-features`.
+
+use catplush::catplush;
+use catplush::catplush_main::*;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+use common::test_bitmap;
+
+/// Elements nested inside each other, like a generated tree view.
+fn deep(ui: &mut CatplushContext, depth: usize) {
+    for _ in 0..depth {
+        ui.open_element(UiElement::new().rectangle(ObjectColor::WHITE, CornerRadius::default()).padding(Padding::all(1)));
+    }
+    catplush!(ui, { UiElement::new().sizing(SizingMode::Fixed(10), SizingMode::Fixed(10)) });
+    for _ in 0..depth {
+        ui.close_element();
+    }
+}
+
+/// A long list of rows with a label each.
+fn wide(ui: &mut CatplushContext, bitmap: &BitmapConfiguration, rows: u32) {
+    catplush!(ui, {
+        UiElement::new().sizing(SizingMode::Grow, SizingMode::Fit).layout_direction(ChildLayoutDirection::TopToBottom).child_gap(2) => {
+            @{
+                for row in 0..rows {
+                    catplush!(ui, {
+                        UiElement::new()
+                            .id_indexed("row", row)
+                            .rectangle(ObjectColor::WHITE, CornerRadius::default())
+                            .sizing(SizingMode::Grow, SizingMode::Fit)
+                            .padding(Padding::all(4)) => {
+                            UiElement::new().text(bitmap, "row label", 16, 1.2);
+                        }
+                    });
+                }
+            }
+        }
+    });
+}
+
+/// Runs `frame` until a second has passed and prints how long it took on average.
+fn bench(name: &str, mut frame: impl FnMut()) {
+    // Warming up, which also fills the layout cache
+    for _ in 0..3 {
+        frame();
+    }
+
+    let start = Instant::now();
+    let mut iterations = 0;
+    while start.elapsed() < Duration::from_secs(1) {
+        frame();
+        iterations += 1;
+    }
+
+    println!("{name:<40} {:>12.3?} per frame ({iterations} frames)", start.elapsed() / iterations);
+}
+
+fn main() {
+    let bitmap = test_bitmap();
+
+    for depth in [1_000, 10_000, 100_000] {
+        bench(&format!("deep {depth}"), || {
+            let mut ui = CatplushContext::begin_layout((1200, 700), ChildLayoutDirection::LeftToRight);
+            deep(&mut ui, depth);
+            black_box(ui.end_layout().len());
+        });
+    }
+
+    for rows in [1_000, 10_000, 100_000] {
+        bench(&format!("wide {rows}"), || {
+            let mut ui = CatplushContext::begin_layout((1200, 700), ChildLayoutDirection::TopToBottom);
+            wide(&mut ui, &bitmap, rows);
+            black_box(ui.end_layout().len());
+        });
+
+        // Kept between frames, so the rows come out of the layout cache
+        let mut ui = CatplushContext::new();
+        bench(&format!("wide {rows}, persistent context"), || {
+            ui.reset((1200, 700), ChildLayoutDirection::TopToBottom);
+            wide(&mut ui, &bitmap, rows);
+            ui.solve();
+            black_box(ui.render_commands().count());
+        });
+    }
+}
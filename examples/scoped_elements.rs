@@ -0,0 +1,28 @@
+//! Builds a layout with `element` and `open_scope`, which close their elements on their own,
+//! and prints it with the terminal backend so it runs without a window.
+
+use catplush::catplush_main::*;
+use catplush::catplush_tui::TerminalBackend;
+
+fn panel() -> UiElement {
+    UiElement::new()
+        .rectangle(ObjectColor::TRANSPARENT, CornerRadius::all(4.0))
+        .border(ObjectColor::WHITE, BorderWidth::all(1))
+}
+
+fn main() {
+    let mut backend = TerminalBackend::new(40, 10, 8.0, 16.0);
+    let mut ui = CatplushContext::begin_layout(backend.layout_size(), ChildLayoutDirection::LeftToRight);
+
+    // The closure gets the context back to add children with, and the element is closed once it returns
+    ui.element(panel().sizing(SizingMode::Grow, SizingMode::Grow).padding(Padding::all(16)).child_gap(16), |ui| {
+        ui.element(panel().sizing(SizingMode::Fixed(80), SizingMode::Grow), |_| {});
+
+        // The guard closes its element when it's dropped at the end of the closure
+        let mut content = ui.open_scope(panel().sizing(SizingMode::Grow, SizingMode::Grow).padding(Padding::all(16)));
+        content.element(panel().sizing(SizingMode::Grow, SizingMode::Fixed(32)), |_| {});
+    });
+
+    backend.render(ui.end_layout());
+    print!("{}", backend.to_plain_string());
+}
@@ -0,0 +1,47 @@
+//! Helpers shared by the backends that write layouts out as text documents.
+
+/// Rounds to two decimal places so the document doesn't fill up with float noise. Adding zero
+/// turns `-0` into `0`, which would otherwise be written out with its sign.
+pub(crate) fn number(value: f32) -> f32 {
+    (value * 100.0).round() / 100.0 + 0.0
+}
+
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(char)
+        }
+    }
+    escaped
+}
+
+/// Works out whether the data is a JPEG or a PNG from its first bytes and base64 encodes it.
+pub(crate) fn data_uri(image_data: &[u8]) -> String {
+    let mime_type = if image_data.starts_with(&[0xff, 0xd8]) { "image/jpeg" } else { "image/png" };
+    format!("data:{mime_type};base64,{}", base64_encode(image_data))
+}
+
+pub(crate) fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let combined = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(combined >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,164 @@
+use crate::catplush_main::*;
+use crate::catplush_export::{data_uri, escape_xml, number};
+use std::{collections::HashMap, fmt::Write, num::NonZeroU32};
+use glam::Vec2;
+
+/// Writes render commands out as absolutely positioned HTML elements, for sharing mock-ups and
+/// comparing layouts in a browser.
+pub struct HtmlBackend {
+    width: u32,
+    height: u32,
+    text_color: ObjectColor,
+    background: Option<ObjectColor>,
+
+    images: HashMap<NonZeroU32, HtmlImage>,
+    body: String
+}
+
+struct HtmlImage {
+    /// Size of the whole texture in pixels
+    size: Vec2,
+    source: String
+}
+
+impl HtmlBackend {
+    pub fn new(width: u32, height: u32) -> Self {
+        HtmlBackend {
+            width,
+            height,
+            text_color: ObjectColor::WHITE,
+            background: None,
+
+            images: HashMap::new(),
+            body: String::new()
+        }
+    }
+
+    /// Bitmap fonts don't have a color of their own, so this is used for all text. White by default.
+    pub fn text_color(mut self, color: ObjectColor) -> Self {
+        self.text_color = color;
+        self
+    }
+
+    pub fn background(mut self, color: ObjectColor) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    /// Embeds a PNG or JPEG file as a data URI for everything drawn with this texture. The texture
+    /// has to be the whole image the file was loaded into, not a sub-rectangle of it.
+    pub fn add_image(&mut self, texture: CatplushTextureData, image_data: &[u8]) {
+        self.add_image_url(texture, &data_uri(image_data));
+    }
+
+    /// Like `add_image`, but links to the image instead of embedding it.
+    pub fn add_image_url(&mut self, texture: CatplushTextureData, url: &str) {
+        let size = Vec2::new(texture.width as f32, texture.height as f32) / texture.uv_size;
+        self.images.insert(texture.texture_id, HtmlImage { size, source: escape_xml(url) });
+    }
+
+    /// Throws away everything drawn so far.
+    pub fn clear(&mut self) {
+        self.body.clear();
+    }
+
+    /// Returns a whole HTML page with the layout in it.
+    pub fn to_html(&self) -> String {
+        let mut document = String::new();
+        document.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>catplush layout</title>\n</head>\n<body style=\"margin: 0\">\n");
+
+        let background = self.background.map(|color| format!(" background: {};", css_color(color))).unwrap_or_default();
+        let _ = writeln!(
+            document,
+            r#"<div style="position: relative; overflow: hidden; width: {}px; height: {}px;{}">"#,
+            self.width, self.height, background
+        );
+
+        document.push_str(&self.body);
+        document.push_str("</div>\n</body>\n</html>\n");
+        document
+    }
+}
+
+impl RenderBackend for HtmlBackend {
+    fn draw_rectangle(&mut self, bounding_box: BoundingBox, data: &RectangleRenderData) {
+        let radius = data.corner_radius;
+        let border = data.border_width;
+
+        let mut style = position_style(bounding_box);
+        let _ = write!(
+            style,
+            " box-sizing: border-box; background: {}; border-radius: {}px {}px {}px {}px;",
+            css_color(data.color), number(radius.top_left), number(radius.top_right), number(radius.bottom_right), number(radius.bottom_left)
+        );
+
+        if border.top > 0 || border.right > 0 || border.bottom > 0 || border.left > 0 {
+            let _ = write!(
+                style,
+                " background-clip: padding-box; border-style: solid; border-color: {}; border-width: {}px {}px {}px {}px;",
+                css_color(data.stroke_color), border.top, border.right, border.bottom, border.left
+            );
+        }
+
+        let _ = writeln!(self.body, r#"<div style="{style}"></div>"#);
+    }
+
+    /// Each line gets its own element so the text wraps in the same places as in the layout.
+    fn draw_text(&mut self, bounding_box: BoundingBox, data: &TextRenderData) {
+        let glyph_size = data.bitmap.cell_size * data.scale_factor();
+        let line_advance = glyph_size.y * data.line_height;
+
+        for (line_number, line) in data.lines().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+
+            let line_box = BoundingBox {
+                x: bounding_box.x,
+                y: bounding_box.y + line_advance * line_number as f32,
+                width: glyph_size.x * line.chars().count() as f32,
+                height: glyph_size.y
+            };
+            let _ = writeln!(
+                self.body,
+                r#"<span style="{} font: {}px/{}px monospace; white-space: pre; color: {};">{}</span>"#,
+                position_style(line_box), number(glyph_size.y), number(glyph_size.y), css_color(self.text_color), escape_xml(line)
+            );
+        }
+    }
+
+    /// Sub-rectangles are shown by scaling the whole image and cropping it with a wrapper.
+    fn draw_image(&mut self, _bounding_box: BoundingBox, data: &TextureRenderData) {
+        let Some(image) = self.images.get(&data.texture_id) else {
+            log::warn!("Texture {} was never added to the html backend, skipping it", data.texture_id);
+            return;
+        };
+
+        let view_pos = data.uv_pos * image.size;
+        let view_size = data.uv_size * image.size;
+        if !(view_size.x > 0.0 && view_size.y > 0.0 && view_size.is_finite()) {
+            return;
+        }
+        let scale = Vec2::new(data.draw_box.width, data.draw_box.height) / view_size;
+        let image_size = image.size * scale;
+        let image_offset = -view_pos * scale;
+
+        let _ = writeln!(
+            self.body,
+            r#"<div style="{} overflow: hidden;"><img src="{}" alt="" style="position: absolute; left: {}px; top: {}px; width: {}px; height: {}px; max-width: none;"></div>"#,
+            position_style(data.draw_box), image.source,
+            number(image_offset.x), number(image_offset.y), number(image_size.x), number(image_size.y)
+        );
+    }
+}
+
+fn position_style(bounding_box: BoundingBox) -> String {
+    format!(
+        "position: absolute; left: {}px; top: {}px; width: {}px; height: {}px;",
+        number(bounding_box.x), number(bounding_box.y), number(bounding_box.width), number(bounding_box.height)
+    )
+}
+
+fn css_color(color: ObjectColor) -> String {
+    format!("rgba({}, {}, {}, {})", color.0, color.1, color.2, number(color.3 as f32 / 255.0))
+}
@@ -0,0 +1,654 @@
+//! A small text format for writing screens without recompiling. A file is a list of elements, each
+//! written as its kind followed by its properties in brackets:
+//!
+//! ```text
+//! // Comments start with two slashes
+//! element(
+//!     sizing: (grow, fixed(40)),
+//!     padding: (10, 5),
+//!     gap: 10,
+//!     direction: top_to_bottom,
+//!     align: (center, top),
+//!     color: #1a4357ff,
+//!     radius: 15,
+//!     border: (#eebe2b, 3),
+//!     children: [
+//!         text("Cards", font: "uiua", size: 22, line_height: 1.2),
+//!         image("awakened", width: 30, fit: contain),
+//!         nine_slice("panel", insets: 8, sizing: (grow, grow)),
+//!     ],
+//! )
+//! ```
+//!
+//! Fonts and images are looked up by name in `MarkupResources`. Brackets can be nested up to 100 deep,
+//! which is 50 elements inside of each other.
+
+use crate::catplush_main::*;
+use std::{collections::HashMap, fs, path::PathBuf, sync::Arc, time::SystemTime};
+
+//////////////// Resources ////////////////
+
+/// Fonts and textures that markup can refer to by name.
+#[derive(Default)]
+pub struct MarkupResources {
+    /// Shared with every text element made from them instead of copied into each one
+    fonts: HashMap<String, Arc<BitmapConfiguration>>,
+    images: HashMap<String, CatplushTextureData>
+}
+
+impl MarkupResources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// If there's only one font, text elements can leave out `font:` and get this one.
+    pub fn font(mut self, name: &str, bitmap: &BitmapConfiguration) -> Self {
+        self.fonts.insert(name.to_string(), Arc::new(bitmap.clone()));
+        self
+    }
+
+    /// Used by `image` and `nine_slice` elements. Sprite sheet frames and atlas entries work too.
+    pub fn image(mut self, name: &str, texture: CatplushTextureData) -> Self {
+        self.images.insert(name.to_string(), texture);
+        self
+    }
+}
+
+//////////////// Markup ////////////////
+
+/// A parsed markup file, ready to be added to a layout as many times as needed.
+#[derive(Clone)]
+pub struct Markup {
+    roots: Vec<MarkupNode>
+}
+
+#[derive(Clone)]
+struct MarkupNode {
+    element: UiElement,
+    children: Vec<MarkupNode>
+}
+
+impl Markup {
+    /// Parses markup and looks up every font and image it uses, so any mistake is found here
+    /// instead of while building the layout.
+    pub fn parse(source: &str, resources: &MarkupResources) -> Result<Self, CatplushError> {
+        let mut parser = Parser::new(source);
+        let mut roots = vec![];
+
+        parser.skip_whitespace();
+        while !parser.at_end() {
+            let value = parser.value()?;
+            roots.push(build_node(&value, resources)?);
+
+            parser.skip_whitespace();
+            if parser.peek() == Some(',') {
+                parser.advance();
+                parser.skip_whitespace();
+            }
+        }
+
+        Ok(Markup { roots })
+    }
+
+    /// Opens and closes all of the elements inside of whatever element is currently open.
+    pub fn build(&self, ui: &mut CatplushContext) {
+        for root in &self.roots {
+            root.build(ui);
+        }
+    }
+}
+
+impl MarkupNode {
+    fn build(&self, ui: &mut CatplushContext) {
+        ui.open_element(self.element.clone());
+        for child in &self.children {
+            child.build(ui);
+        }
+        ui.close_element();
+    }
+}
+
+//////////////// Hot Reloading ////////////////
+
+/// A markup file that gets parsed again whenever it changes on disk. Call `reload_if_changed` once
+/// a frame, which only checks the file's modification time unless it changed.
+pub struct MarkupFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    markup: Markup
+}
+
+impl MarkupFile {
+    pub fn load(path: impl Into<PathBuf>, resources: &MarkupResources) -> Result<Self, CatplushError> {
+        let path = path.into();
+        let modified = fs::metadata(&path)?.modified().ok();
+        let markup = Markup::parse(&fs::read_to_string(&path)?, resources)?;
+
+        Ok(MarkupFile { path, modified, markup })
+    }
+
+    /// Returns whether the file was reloaded. If the new version has a mistake in it, the old
+    /// version is kept and the error is returned, so a half-saved file doesn't break the screen.
+    pub fn reload_if_changed(&mut self, resources: &MarkupResources) -> Result<bool, CatplushError> {
+        let modified = fs::metadata(&self.path)?.modified().ok();
+        if modified == self.modified {
+            return Ok(false);
+        }
+
+        // Only try each version of the file once, even if it fails to parse
+        self.modified = modified;
+        self.markup = Markup::parse(&fs::read_to_string(&self.path)?, resources)?;
+        Ok(true)
+    }
+
+    pub fn markup(&self) -> &Markup {
+        &self.markup
+    }
+
+    pub fn build(&self, ui: &mut CatplushContext) {
+        self.markup.build(ui);
+    }
+}
+
+//////////////// Building Elements ////////////////
+
+fn build_node(value: &Value, resources: &MarkupResources) -> Result<MarkupNode, CatplushError> {
+    let ValueKind::Call { name, positional, named } = &value.kind else {
+        return Err(value.error("expected an element, like `element(...)`"));
+    };
+
+    let positional_arg = |kind: &str| -> Result<&Value, CatplushError> {
+        match positional.as_slice() {
+            [argument] => Ok(argument),
+            _ => Err(value.error(&format!("`{kind}` takes one unnamed value before its properties")))
+        }
+    };
+    let property = |key: &str| named.iter().find(|(name, _)| matches!(&name.kind, ValueKind::Ident(name) if name == key)).map(|(_, value)| value);
+
+    let mut element = match name.as_str() {
+        "element" => {
+            if let Some(argument) = positional.first() {
+                return Err(argument.error("`element` only takes named properties"));
+            }
+            UiElement::new()
+        },
+        "text" => {
+            let text_value = positional_arg("text")?;
+            let text = text_value.as_str()?;
+            let bitmap = match property("font") {
+                Some(font) => resources.fonts.get(font.as_str()?).ok_or_else(|| font.error("no font with this name was added to the resources"))?,
+                None if resources.fonts.len() == 1 => resources.fonts.values().next().unwrap(),
+                None => return Err(value.error("text needs a `font` unless exactly one font was added to the resources"))
+            };
+
+            // Checked here since `text` panics on characters the bitmap doesn't have
+            if let Some(missing) = text.chars().find(|&char| char != '\n' && !bitmap.character_list.contains(char)) {
+                return Err(text_value.error(&format!("the font doesn't have the character {missing:?}")));
+            }
+
+            let font_size = match property("size") {
+                Some(size) => match size.as_int()? {
+                    font_size if font_size > 0 => font_size as u32,
+                    _ => return Err(size.error("font sizes have to be bigger than 0"))
+                },
+                None => bitmap.cell_size.y as u32
+            };
+            let line_height = match property("line_height") { Some(line_height) => line_height.as_number()?, None => 1.0 };
+            let mut element = UiElement::new().shared_text(bitmap, text, font_size, line_height);
+            if let Some(break_words) = property("break_words") {
+                if break_words.as_bool()? {
+                    element = element.break_words_on_overflow();
+                }
+            }
+            element
+        },
+        "image" => {
+            let texture = image_argument(positional_arg("image")?, resources)?;
+            let width = property("width").map(Value::as_int).transpose()?;
+            let height = property("height").map(Value::as_int).transpose()?;
+            let ignore_aspect_ratio = property("ignore_aspect_ratio").map(Value::as_bool).transpose()?.unwrap_or(false);
+
+            let mut element = UiElement::new().image(texture, width, height, ignore_aspect_ratio);
+            if let Some(fit) = property("fit") {
+                element = element.image_fit(match fit.as_ident()? {
+                    "fill" => ImageFit::Fill,
+                    "contain" => ImageFit::Contain,
+                    "cover" => ImageFit::Cover,
+                    "none" => ImageFit::None,
+                    _ => return Err(fit.error("expected `fill`, `contain`, `cover` or `none`"))
+                });
+            }
+            element
+        },
+        "nine_slice" => {
+            let texture = image_argument(positional_arg("nine_slice")?, resources)?;
+            let insets = match property("insets") {
+                Some(insets) => {
+                    let [top, right, bottom, left] = insets.sides()?;
+                    NineSliceInsets::new(top, right, bottom, left)
+                },
+                None => return Err(value.error("`nine_slice` needs `insets`"))
+            };
+            UiElement::new().nine_slice(texture, insets)
+        },
+        _ => return Err(value.error(&format!("unknown element `{name}`, expected `element`, `text`, `image` or `nine_slice`")))
+    };
+
+    let mut color = None;
+    let mut corner_radius = CornerRadius::default();
+    let mut children = vec![];
+
+    for (key_value, property) in named {
+        let key = key_value.as_ident()?;
+        match key {
+            "sizing" => {
+                let [width, height] = property.pair()?;
+                element = element.sizing(width.as_sizing()?, height.as_sizing()?);
+            },
+            "width" if name == "element" => element.layout.sizing.width = property.as_sizing()?,
+            "height" if name == "element" => element.layout.sizing.height = property.as_sizing()?,
+            "limit_width" => {
+                let [min, max] = property.pair()?;
+                element = element.limit_width(min.as_int()?, max.as_int()?);
+            },
+            "limit_height" => {
+                let [min, max] = property.pair()?;
+                element = element.limit_height(min.as_int()?, max.as_int()?);
+            },
+            "padding" => {
+                let values = property.numbers()?;
+                element = element.padding(match values.as_slice() {
+                    [all] => Padding::all(*all),
+                    [left_right, top_bottom] => Padding::hv(*left_right, *top_bottom),
+                    [left, right, top, bottom] => Padding::new(*left, *right, *top, *bottom),
+                    _ => return Err(property.error("padding is one number, (horizontal, vertical) or (left, right, top, bottom)"))
+                });
+            },
+            "gap" => element = element.child_gap(property.as_int()?),
+            "direction" => element = element.layout_direction(match property.as_ident()? {
+                "left_to_right" => ChildLayoutDirection::LeftToRight,
+                "top_to_bottom" => ChildLayoutDirection::TopToBottom,
+                _ => return Err(property.error("expected `left_to_right` or `top_to_bottom`"))
+            }),
+            "align" => {
+                let [x, y] = property.pair()?;
+                let x_align = match x.as_ident()? {
+                    "left" => ChildXAlignment::Left,
+                    "center" => ChildXAlignment::Center,
+                    "right" => ChildXAlignment::Right,
+                    _ => return Err(x.error("expected `left`, `center` or `right`"))
+                };
+                let y_align = match y.as_ident()? {
+                    "top" => ChildYAlignment::Top,
+                    "center" => ChildYAlignment::Center,
+                    "bottom" => ChildYAlignment::Bottom,
+                    _ => return Err(y.error("expected `top`, `center` or `bottom`"))
+                };
+                element = element.alignment(x_align, y_align);
+            },
+            "grow_unevenly" => {
+                if property.as_bool()? {
+                    element = element.grow_elements_unevenly();
+                }
+            },
+            "color" if name == "element" => color = Some(property.as_color()?),
+            "radius" if name == "element" => {
+                corner_radius = match &property.kind {
+                    ValueKind::Tuple(_) => {
+                        let [top_right, bottom_right, bottom_left, top_left] = property.sides()?;
+                        CornerRadius::new(top_right as f32, bottom_right as f32, bottom_left as f32, top_left as f32)
+                    },
+                    _ => CornerRadius::all(property.as_number()?)
+                };
+            },
+            "border" => {
+                let [stroke_color, width] = property.pair()?;
+                let border_width = match &width.kind {
+                    ValueKind::Tuple(_) => {
+                        let [top, right, bottom, left] = width.sides()?;
+                        BorderWidth::new(top, right, bottom, left)
+                    },
+                    _ => BorderWidth::all(width.as_int()?)
+                };
+                element = element.border(stroke_color.as_color()?, border_width);
+            },
+            "children" => {
+                let ValueKind::List(values) = &property.kind else {
+                    return Err(property.error("children go in a list, like `children: [...]`"));
+                };
+                if name != "element" {
+                    return Err(property.error(&format!("`{name}` elements can't have children")));
+                }
+                children = values.iter().map(|child| build_node(child, resources)).collect::<Result<_, _>>()?;
+            },
+            // Already handled above
+            "font" | "size" | "line_height" | "break_words" if name == "text" => {},
+            "width" | "height" | "ignore_aspect_ratio" | "fit" if name == "image" => {},
+            "insets" if name == "nine_slice" => {},
+            _ => return Err(key_value.error(&format!("`{name}` doesn't have a `{key}` property")))
+        }
+    }
+
+    if let Some(color) = color {
+        element = element.rectangle(color, corner_radius);
+    }
+
+    Ok(MarkupNode { element, children })
+}
+
+fn image_argument(value: &Value, resources: &MarkupResources) -> Result<CatplushTextureData, CatplushError> {
+    resources.images.get(value.as_str()?).copied().ok_or_else(|| value.error("no image with this name was added to the resources"))
+}
+
+//////////////// Parsing ////////////////
+
+struct Value {
+    kind: ValueKind,
+    line: usize,
+    column: usize
+}
+
+enum ValueKind {
+    Number(f32),
+    String(String),
+    Color(ObjectColor),
+    Ident(String),
+    /// Named properties are kept with their names as `Ident`s, so mistakes in them can be pointed at
+    Call { name: String, positional: Vec<Value>, named: Vec<(Value, Value)> },
+    Tuple(Vec<Value>),
+    List(Vec<Value>)
+}
+
+impl Value {
+    fn error(&self, message: &str) -> CatplushError {
+        CatplushError::Markup { line: self.line, column: self.column, message: message.to_string() }
+    }
+
+    fn as_number(&self) -> Result<f32, CatplushError> {
+        match self.kind {
+            ValueKind::Number(number) => Ok(number),
+            _ => Err(self.error("expected a number"))
+        }
+    }
+
+    fn as_int(&self) -> Result<i32, CatplushError> {
+        match self.kind {
+            ValueKind::Number(number) if number.fract() == 0.0 => Ok(number as i32),
+            _ => Err(self.error("expected a whole number"))
+        }
+    }
+
+    fn as_str(&self) -> Result<&str, CatplushError> {
+        match &self.kind {
+            ValueKind::String(string) => Ok(string),
+            _ => Err(self.error("expected a string in double quotes"))
+        }
+    }
+
+    fn as_ident(&self) -> Result<&str, CatplushError> {
+        match &self.kind {
+            ValueKind::Ident(ident) => Ok(ident),
+            _ => Err(self.error("expected a name"))
+        }
+    }
+
+    fn as_bool(&self) -> Result<bool, CatplushError> {
+        match self.as_ident() {
+            Ok("true") => Ok(true),
+            Ok("false") => Ok(false),
+            _ => Err(self.error("expected `true` or `false`"))
+        }
+    }
+
+    fn as_color(&self) -> Result<ObjectColor, CatplushError> {
+        match self.kind {
+            ValueKind::Color(color) => Ok(color),
+            _ => Err(self.error("expected a color, like #rrggbb or #rrggbbaa"))
+        }
+    }
+
+    /// `grow`, `fit`, `fixed(100)` or just a number for a fixed size.
+    fn as_sizing(&self) -> Result<SizingMode, CatplushError> {
+        match &self.kind {
+            ValueKind::Ident(ident) if ident == "grow" => Ok(SizingMode::Grow),
+            ValueKind::Ident(ident) if ident == "fit" => Ok(SizingMode::Fit),
+            ValueKind::Call { name, positional, named } if name == "fixed" && named.is_empty() && positional.len() == 1 => {
+                Ok(SizingMode::Fixed(positional[0].as_int()?))
+            },
+            ValueKind::Number(_) => Ok(SizingMode::Fixed(self.as_int()?)),
+            _ => Err(self.error("expected `grow`, `fit` or `fixed(size)`"))
+        }
+    }
+
+    fn pair(&self) -> Result<[&Value; 2], CatplushError> {
+        match &self.kind {
+            ValueKind::Tuple(values) if values.len() == 2 => Ok([&values[0], &values[1]]),
+            _ => Err(self.error("expected two values in brackets, like (a, b)"))
+        }
+    }
+
+    /// One number, or a tuple of whole numbers.
+    fn numbers(&self) -> Result<Vec<i32>, CatplushError> {
+        match &self.kind {
+            ValueKind::Tuple(values) => values.iter().map(Value::as_int).collect(),
+            _ => Ok(vec![self.as_int()?])
+        }
+    }
+
+    /// One number for all four sides, or all four of them.
+    fn sides(&self) -> Result<[i32; 4], CatplushError> {
+        match self.numbers()?.as_slice() {
+            [all] => Ok([*all; 4]),
+            [a, b, c, d] => Ok([*a, *b, *c, *d]),
+            _ => Err(self.error("expected one number or four of them"))
+        }
+    }
+}
+
+/// How deep brackets can be nested. Parsing and building recurse once per level, so deeper markup
+/// is an error instead of a stack overflow.
+const MAX_DEPTH: usize = 100;
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+    column: usize,
+    /// How many values the one being parsed is inside of
+    depth: usize
+}
+
+impl Parser {
+    fn new(source: &str) -> Self {
+        Parser { chars: source.chars().collect(), position: 0, line: 1, column: 1, depth: 0 }
+    }
+
+    fn at_end(&self) -> bool {
+        self.position >= self.chars.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let char = self.peek()?;
+        self.position += 1;
+        if char == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(char)
+    }
+
+    fn error(&self, message: &str) -> CatplushError {
+        CatplushError::Markup { line: self.line, column: self.column, message: message.to_string() }
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            match self.peek() {
+                Some(char) if char.is_whitespace() => { self.advance(); },
+                Some('/') if self.chars.get(self.position + 1) == Some(&'/') => {
+                    while !matches!(self.peek(), None | Some('\n')) {
+                        self.advance();
+                    }
+                },
+                _ => return
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), CatplushError> {
+        self.skip_whitespace();
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected `{expected}`")));
+        }
+        self.advance();
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value, CatplushError> {
+        self.skip_whitespace();
+        let (line, column) = (self.line, self.column);
+
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(&format!("brackets can't be nested more than {MAX_DEPTH} deep")));
+        }
+        self.depth += 1;
+
+        let kind = match self.peek() {
+            None => return Err(self.error("expected a value, found the end of the file")),
+            Some('"') => ValueKind::String(self.string()?),
+            Some('#') => ValueKind::Color(self.color()?),
+            Some('(') => {
+                self.advance();
+                ValueKind::Tuple(self.values(')')?)
+            },
+            Some('[') => {
+                self.advance();
+                ValueKind::List(self.values(']')?)
+            },
+            Some(char) if char.is_ascii_digit() || char == '-' || char == '.' => ValueKind::Number(self.number()?),
+            Some(char) if char.is_alphabetic() || char == '_' => {
+                let name = self.ident();
+                if self.peek() == Some('(') {
+                    self.advance();
+                    self.call(name)?
+                } else {
+                    ValueKind::Ident(name)
+                }
+            },
+            Some(char) => return Err(self.error(&format!("unexpected `{char}`")))
+        };
+
+        self.depth -= 1;
+        Ok(Value { kind, line, column })
+    }
+
+    /// Comma separated values up to the closing bracket, allowing a comma after the last one.
+    fn values(&mut self, close: char) -> Result<Vec<Value>, CatplushError> {
+        let mut values = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(close) {
+                self.advance();
+                return Ok(values);
+            }
+
+            values.push(self.value()?);
+            self.skip_whitespace();
+            if self.peek() != Some(close) {
+                self.expect(',')?;
+            }
+        }
+    }
+
+    fn call(&mut self, name: String) -> Result<ValueKind, CatplushError> {
+        let mut positional = vec![];
+        let mut named = vec![];
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(')') {
+                self.advance();
+                return Ok(ValueKind::Call { name, positional, named });
+            }
+
+            let value = self.value()?;
+            self.skip_whitespace();
+
+            if self.peek() == Some(':') {
+                if !matches!(value.kind, ValueKind::Ident(_)) {
+                    return Err(value.error("property names can't be quoted or have brackets"));
+                }
+                self.advance();
+                named.push((value, self.value()?));
+            } else if named.is_empty() {
+                positional.push(value);
+            } else {
+                return Err(value.error("unnamed values have to come before the named properties"));
+            }
+
+            self.skip_whitespace();
+            if self.peek() != Some(')') {
+                self.expect(',')?;
+            }
+        }
+    }
+
+    fn ident(&mut self) -> String {
+        let mut ident = String::new();
+        while let Some(char) = self.peek().filter(|char| char.is_alphanumeric() || *char == '_') {
+            ident.push(char);
+            self.advance();
+        }
+        ident
+    }
+
+    fn number(&mut self) -> Result<f32, CatplushError> {
+        let mut number = String::new();
+        while let Some(char) = self.peek().filter(|char| char.is_ascii_digit() || *char == '-' || *char == '.') {
+            number.push(char);
+            self.advance();
+        }
+        number.parse().map_err(|_| self.error(&format!("`{number}` isn't a number")))
+    }
+
+    fn string(&mut self) -> Result<String, CatplushError> {
+        self.advance();
+        let mut string = String::new();
+
+        loop {
+            match self.advance() {
+                None => return Err(self.error("the string is never closed")),
+                Some('"') => return Ok(string),
+                Some('\\') => match self.advance() {
+                    Some('n') => string.push('\n'),
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    _ => return Err(self.error("unknown escape, expected \\n, \\\" or \\\\"))
+                },
+                Some(char) => string.push(char)
+            }
+        }
+    }
+
+    /// `#rrggbb` or `#rrggbbaa`.
+    fn color(&mut self) -> Result<ObjectColor, CatplushError> {
+        self.advance();
+        let mut hex = String::new();
+        while let Some(char) = self.peek().filter(char::is_ascii_hexdigit) {
+            hex.push(char);
+            self.advance();
+        }
+
+        match (hex.len(), u32::from_str_radix(&hex, 16)) {
+            (6, Ok(rgb)) => Ok(ObjectColor::from_u32_hex(rgb << 8 | 0xff)),
+            (8, Ok(rgba)) => Ok(ObjectColor::from_u32_hex(rgba)),
+            _ => Err(self.error("colors have 6 or 8 hex digits"))
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use crate::catplush_main::*;
+use std::{collections::HashMap, num::NonZeroU32};
+use glam::{Vec2, Vec4};
+
+/// Draws render commands into an RGBA8 buffer on the CPU, for when there's no GPU around, like in
+/// snapshot tests, thumbnails or server-side previews.
+pub struct SoftwareBackend {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    clear_color: ObjectColor,
+
+    textures: HashMap<NonZeroU32, SoftwareTexture>,
+    next_texture_id: u32
+}
+
+struct SoftwareTexture {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>
+}
+
+impl SoftwareBackend {
+    /// Returns a transparent buffer of the given size.
+    pub fn new(width: u32, height: u32) -> Self {
+        SoftwareBackend {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+            clear_color: ObjectColor::TRANSPARENT,
+
+            textures: HashMap::new(),
+            next_texture_id: 1
+        }
+    }
+
+    pub fn set_clear_color(&mut self, color: ObjectColor) {
+        self.clear_color = color;
+    }
+
+    /// Fills the whole buffer with the clear color.
+    pub fn clear(&mut self) {
+        let color = [self.clear_color.0, self.clear_color.1, self.clear_color.2, self.clear_color.3];
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+
+    /// Changes the size of the buffer and clears it.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.pixels.resize(width as usize * height as usize * 4, 0);
+        self.clear();
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The drawn image as RGBA8, row by row from the top.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Returns `None` outside of the buffer.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<ObjectColor> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let index = self.pixel_index(x, y);
+        Some(ObjectColor(self.pixels[index], self.pixels[index + 1], self.pixels[index + 2], self.pixels[index + 3]))
+    }
+
+    fn pixel_index(&self, x: u32, y: u32) -> usize {
+        (y as usize * self.width as usize + x as usize) * 4
+    }
+
+    /// Stores RGBA8 pixels so images and bitmap fonts can be drawn with them. The returned texture
+    /// works the same way as one loaded onto the GPU.
+    pub fn add_texture(&mut self, width: u32, height: u32, pixels: Vec<u8>) -> Result<CatplushTextureData, CatplushError> {
+        let expected = width as usize * height as usize * 4;
+        if pixels.len() != expected {
+            return Err(CatplushError::InvalidPixelData { expected, actual: pixels.len() });
+        }
+        if width == 0 || height == 0 {
+            return Err(CatplushError::EmptyImage);
+        }
+
+        // Can't be zero since it starts at one
+        let texture_id = NonZeroU32::new(self.next_texture_id).unwrap();
+        self.next_texture_id += 1;
+        self.textures.insert(texture_id, SoftwareTexture { width, height, pixels });
+
+        Ok(CatplushTextureData::new(texture_id, width as i32, height as i32))
+    }
+
+    /// Decodes an image and stores it as a texture.
+    #[cfg(feature = "image")]
+    pub fn load_texture(&mut self, image_data: &[u8]) -> Result<CatplushTextureData, CatplushError> {
+        let image = decode_image(image_data)?.to_rgba8();
+        self.add_texture(image.width(), image.height(), image.into_raw())
+    }
+
+    #[cfg(feature = "image")]
+    pub fn to_image(&self) -> image::RgbaImage {
+        // Can't fail since the buffer is always the right size
+        image::RgbaImage::from_raw(self.width, self.height, self.pixels.clone()).unwrap()
+    }
+
+    #[cfg(feature = "image")]
+    pub fn save_png(&self, path: impl AsRef<std::path::Path>) -> Result<(), CatplushError> {
+        self.to_image()
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(|error| CatplushError::ImageEncode(Box::new(error)))
+    }
+
+    fn blend_pixel(&mut self, x: u32, y: u32, color: ObjectColor, coverage: f32) {
+        let index = self.pixel_index(x, y);
+        blend(&mut self.pixels[index..index + 4], color, coverage);
+    }
+
+    /// The range of pixels a box touches, cut down to the size of the buffer.
+    fn pixel_range(&self, bounding_box: BoundingBox) -> (u32, u32, u32, u32) {
+        let clamp_x = |value: f32| value.clamp(0.0, self.width as f32) as u32;
+        let clamp_y = |value: f32| value.clamp(0.0, self.height as f32) as u32;
+
+        (
+            clamp_x(bounding_box.x.floor()),
+            clamp_y(bounding_box.y.floor()),
+            clamp_x((bounding_box.x + bounding_box.width).ceil()),
+            clamp_y((bounding_box.y + bounding_box.height).ceil())
+        )
+    }
+}
+
+impl RenderBackend for SoftwareBackend {
+    /// The border is drawn inside of the rectangle, and the corners of the inside are rounded by
+    /// however much of the corner radius is left after the border.
+    fn draw_rectangle(&mut self, bounding_box: BoundingBox, data: &RectangleRenderData) {
+        let border = data.border_width;
+        let has_border = border.top > 0 || border.right > 0 || border.bottom > 0 || border.left > 0;
+
+        let outer_radius = data.corner_radius.as_vec4();
+        let inner_box = BoundingBox {
+            x: bounding_box.x + border.left as f32,
+            y: bounding_box.y + border.top as f32,
+            width: bounding_box.width - (border.left + border.right) as f32,
+            height: bounding_box.height - (border.top + border.bottom) as f32
+        };
+        // top right, bottom right, bottom left, top left
+        let inner_radius = (outer_radius - Vec4::new(
+            border.top.max(border.right) as f32,
+            border.bottom.max(border.right) as f32,
+            border.bottom.max(border.left) as f32,
+            border.top.max(border.left) as f32
+        )).max(Vec4::ZERO);
+
+        let (min_x, min_y, max_x, max_y) = self.pixel_range(bounding_box);
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let point = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let outer_coverage = coverage(rounded_box_distance(point, bounding_box, outer_radius));
+
+                if !has_border {
+                    self.blend_pixel(x, y, data.color, outer_coverage);
+                    continue;
+                }
+
+                let inner_coverage =
+                    if inner_box.width > 0.0 && inner_box.height > 0.0 { coverage(rounded_box_distance(point, inner_box, inner_radius)) }
+                    else { 0.0 };
+
+                self.blend_pixel(x, y, data.stroke_color, (outer_coverage - inner_coverage).max(0.0));
+                self.blend_pixel(x, y, data.color, inner_coverage);
+            }
+        }
+    }
+
+    fn draw_text(&mut self, bounding_box: BoundingBox, data: &TextRenderData) {
+        for glyph in data.glyphs(Vec2::new(bounding_box.x, bounding_box.y)) {
+            self.draw_image(bounding_box, &glyph);
+        }
+    }
+
+    /// Uses nearest neighbour sampling, which keeps bitmap fonts and pixel art sharp.
+    fn draw_image(&mut self, _bounding_box: BoundingBox, data: &TextureRenderData) {
+        let draw_box = data.draw_box;
+        let (min_x, min_y, max_x, max_y) = self.pixel_range(draw_box);
+
+        let Some(texture) = self.textures.get(&data.texture_id) else {
+            log::warn!("Texture {} was never added to the software backend, skipping it", data.texture_id);
+            return;
+        };
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let relative = (Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - Vec2::new(draw_box.x, draw_box.y)) / Vec2::new(draw_box.width, draw_box.height);
+                if relative.x < 0.0 || relative.y < 0.0 || relative.x >= 1.0 || relative.y >= 1.0 {
+                    continue;
+                }
+
+                let uv = data.uv_pos + relative * data.uv_size;
+                let texel_x = ((uv.x * texture.width as f32) as u32).min(texture.width - 1);
+                let texel_y = ((uv.y * texture.height as f32) as u32).min(texture.height - 1);
+                let index = (texel_y as usize * texture.width as usize + texel_x as usize) * 4;
+                let texel = &texture.pixels[index..index + 4];
+
+                // Not `blend_pixel`, since the texture is still borrowed from `self`
+                let pixel_index = (y as usize * self.width as usize + x as usize) * 4;
+                blend(&mut self.pixels[pixel_index..pixel_index + 4], ObjectColor(texel[0], texel[1], texel[2], texel[3]), 1.0);
+            }
+        }
+    }
+}
+
+/// Blends a color over an RGBA8 pixel, with the coverage scaling the color's alpha.
+fn blend(destination: &mut [u8], color: ObjectColor, coverage: f32) {
+    let source_alpha = color.3 as f32 / 255.0 * coverage;
+    if source_alpha <= 0.0 {
+        return;
+    }
+
+    let destination_alpha = destination[3] as f32 / 255.0;
+    let out_alpha = source_alpha + destination_alpha * (1.0 - source_alpha);
+    let source = [color.0, color.1, color.2];
+    for channel in 0..3 {
+        let blended = (source[channel] as f32 * source_alpha + destination[channel] as f32 * destination_alpha * (1.0 - source_alpha)) / out_alpha;
+        destination[channel] = blended.round() as u8;
+    }
+    destination[3] = (out_alpha * 255.0).round() as u8;
+}
+
+/// Signed distance from a point to a box with a different radius on each corner. Negative is inside.
+/// The radii are in the same order as `CornerRadius`: top right, bottom right, bottom left, top left.
+fn rounded_box_distance(point: Vec2, bounding_box: BoundingBox, radii: Vec4) -> f32 {
+    let half_size = Vec2::new(bounding_box.width, bounding_box.height) / 2.0;
+    let center = Vec2::new(bounding_box.x, bounding_box.y) + half_size;
+    let relative = point - center;
+
+    let radius = match (relative.x > 0.0, relative.y > 0.0) {
+        (true, false) => radii.x,
+        (true, true) => radii.y,
+        (false, true) => radii.z,
+        (false, false) => radii.w
+    }.min(half_size.x).min(half_size.y);
+
+    let q = relative.abs() - half_size + Vec2::splat(radius);
+    f32::min(f32::max(q.x, q.y), 0.0) + q.max(Vec2::ZERO).length() - radius
+}
+
+/// How much of a pixel is covered, from a distance to an edge. Gives about one pixel of anti-aliasing.
+fn coverage(distance: f32) -> f32 {
+    (0.5 - distance).clamp(0.0, 1.0)
+}
@@ -0,0 +1,232 @@
+use crate::catplush_main::*;
+use crate::catplush_export::{data_uri, escape_xml, number};
+use std::{collections::HashMap, fmt::Write, num::NonZeroU32};
+use glam::{Vec2, Vec4};
+
+/// How text elements are written into the SVG.
+#[derive(Default, Copy, Clone, PartialEq)]
+pub enum SvgTextMode {
+    /// As `<text>` in a monospace font, stretched to the width of the bitmap glyphs. Easy to read and
+    /// search, but the font won't look like the bitmap.
+    #[default]
+    Text,
+    /// Each character is cut out of the bitmap font's image, so it looks the same as in the app. The
+    /// bitmap's texture has to be added with `add_image`.
+    BitmapGlyphs
+}
+
+/// Writes render commands out as an SVG document, for looking at layouts in a browser.
+pub struct SvgBackend {
+    width: u32,
+    height: u32,
+    text_mode: SvgTextMode,
+    text_color: ObjectColor,
+    background: Option<ObjectColor>,
+
+    images: HashMap<NonZeroU32, SvgImage>,
+    body: String
+}
+
+struct SvgImage {
+    /// Size of the whole texture in pixels
+    size: Vec2,
+    data_uri: String
+}
+
+impl SvgBackend {
+    pub fn new(width: u32, height: u32) -> Self {
+        SvgBackend {
+            width,
+            height,
+            text_mode: SvgTextMode::default(),
+            text_color: ObjectColor::WHITE,
+            background: None,
+
+            images: HashMap::new(),
+            body: String::new()
+        }
+    }
+
+    pub fn text_mode(mut self, text_mode: SvgTextMode) -> Self {
+        self.text_mode = text_mode;
+        self
+    }
+
+    /// Bitmap fonts don't have a color of their own, so this is used for `SvgTextMode::Text`. White by default.
+    pub fn text_color(mut self, color: ObjectColor) -> Self {
+        self.text_color = color;
+        self
+    }
+
+    /// Fills the whole document with a color before anything else is drawn.
+    pub fn background(mut self, color: ObjectColor) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    /// Embeds a PNG or JPEG file as a data URI for everything drawn with this texture. The texture
+    /// has to be the whole image the file was loaded into, not a sub-rectangle of it.
+    pub fn add_image(&mut self, texture: CatplushTextureData, image_data: &[u8]) {
+        let size = Vec2::new(texture.width as f32, texture.height as f32) / texture.uv_size;
+        self.images.insert(texture.texture_id, SvgImage { size, data_uri: data_uri(image_data) });
+    }
+
+    /// Throws away everything drawn so far.
+    pub fn clear(&mut self) {
+        self.body.clear();
+    }
+
+    /// Returns the whole SVG document.
+    pub fn to_svg(&self) -> String {
+        let mut document = String::new();
+        let _ = writeln!(
+            document,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+            self.width, self.height
+        );
+
+        if !self.images.is_empty() {
+            document.push_str("<defs>\n");
+            // Sorted so the same layout always gives the same document
+            let mut texture_ids: Vec<&NonZeroU32> = self.images.keys().collect();
+            texture_ids.sort();
+
+            for texture_id in texture_ids {
+                let image = &self.images[texture_id];
+                let _ = writeln!(
+                    document,
+                    r#"<image id="texture-{}" width="{}" height="{}" href="{}"/>"#,
+                    texture_id, number(image.size.x), number(image.size.y), image.data_uri
+                );
+            }
+            document.push_str("</defs>\n");
+        }
+
+        if let Some(color) = self.background {
+            let _ = writeln!(document, r#"<rect width="100%" height="100%" {}/>"#, fill(color));
+        }
+
+        document.push_str(&self.body);
+        document.push_str("</svg>\n");
+        document
+    }
+
+    /// Draws part of a texture by nesting an `<svg>` whose view box only shows that part. The image
+    /// itself is only written once, in `<defs>`.
+    fn write_texture(&mut self, data: &TextureRenderData) -> bool {
+        let Some(image) = self.images.get(&data.texture_id) else {
+            return false;
+        };
+
+        let view_pos = data.uv_pos * image.size;
+        let view_size = data.uv_size * image.size;
+        let _ = writeln!(
+            self.body,
+            r##"<svg x="{}" y="{}" width="{}" height="{}" viewBox="{} {} {} {}" preserveAspectRatio="none"><use href="#texture-{}"/></svg>"##,
+            number(data.draw_box.x), number(data.draw_box.y), number(data.draw_box.width), number(data.draw_box.height),
+            number(view_pos.x), number(view_pos.y), number(view_size.x), number(view_size.y),
+            data.texture_id
+        );
+
+        true
+    }
+}
+
+impl RenderBackend for SvgBackend {
+    /// Borders are drawn on the inside, as a ring between the outer shape and the inner shape.
+    fn draw_rectangle(&mut self, bounding_box: BoundingBox, data: &RectangleRenderData) {
+        let border = data.border_width;
+        let outer_radius = data.corner_radius.as_vec4();
+        let outer_path = rounded_box_path(bounding_box, outer_radius);
+
+        if border.top <= 0 && border.right <= 0 && border.bottom <= 0 && border.left <= 0 {
+            if data.color.3 > 0 {
+                let _ = writeln!(self.body, r#"<path d="{}" {}/>"#, outer_path, fill(data.color));
+            }
+            return;
+        }
+
+        let inner_box = BoundingBox {
+            x: bounding_box.x + border.left as f32,
+            y: bounding_box.y + border.top as f32,
+            width: (bounding_box.width - (border.left + border.right) as f32).max(0.0),
+            height: (bounding_box.height - (border.top + border.bottom) as f32).max(0.0)
+        };
+        let inner_radius = (outer_radius - Vec4::new(
+            border.top.max(border.right) as f32,
+            border.bottom.max(border.right) as f32,
+            border.bottom.max(border.left) as f32,
+            border.top.max(border.left) as f32
+        )).max(Vec4::ZERO);
+        let inner_path = rounded_box_path(inner_box, inner_radius);
+
+        if data.stroke_color.3 > 0 {
+            let _ = writeln!(self.body, r#"<path d="{} {}" fill-rule="evenodd" {}/>"#, outer_path, inner_path, fill(data.stroke_color));
+        }
+        if data.color.3 > 0 {
+            let _ = writeln!(self.body, r#"<path d="{}" {}/>"#, inner_path, fill(data.color));
+        }
+    }
+
+    fn draw_text(&mut self, bounding_box: BoundingBox, data: &TextRenderData) {
+        match self.text_mode {
+            SvgTextMode::Text => {
+                let glyph_size = data.bitmap.cell_size * data.scale_factor();
+                let line_advance = glyph_size.y * data.line_height;
+
+                for (line_number, line) in data.lines().enumerate() {
+                    if line.is_empty() {
+                        continue;
+                    }
+
+                    // Roughly where the baseline of a monospace font sits in the cell
+                    let baseline = bounding_box.y + line_advance * line_number as f32 + glyph_size.y * 0.8;
+                    let _ = writeln!(
+                        self.body,
+                        r#"<text x="{}" y="{}" font-family="monospace" font-size="{}" textLength="{}" lengthAdjust="spacingAndGlyphs" xml:space="preserve" {}>{}</text>"#,
+                        number(bounding_box.x), number(baseline), number(glyph_size.y),
+                        number(glyph_size.x * line.chars().count() as f32),
+                        fill(self.text_color), escape_xml(line)
+                    );
+                }
+            },
+            SvgTextMode::BitmapGlyphs => {
+                for glyph in data.glyphs(Vec2::new(bounding_box.x, bounding_box.y)) {
+                    self.draw_image(bounding_box, &glyph);
+                }
+            }
+        }
+    }
+
+    fn draw_image(&mut self, _bounding_box: BoundingBox, data: &TextureRenderData) {
+        if !self.write_texture(data) {
+            log::warn!("Texture {} was never added to the svg backend, skipping it", data.texture_id);
+        }
+    }
+}
+
+/// Path for a box with a different radius on each corner, in the same order as `CornerRadius`.
+fn rounded_box_path(bounding_box: BoundingBox, radii: Vec4) -> String {
+    let max_radius = f32::min(bounding_box.width, bounding_box.height) / 2.0;
+    let [top_right, bottom_right, bottom_left, top_left] = radii.min(Vec4::splat(max_radius)).to_array();
+    let (x, y) = (bounding_box.x, bounding_box.y);
+    let (right, bottom) = (x + bounding_box.width, y + bounding_box.height);
+
+    format!(
+        "M{} {} H{} A{tr} {tr} 0 0 1 {} {} V{} A{br} {br} 0 0 1 {} {} H{} A{bl} {bl} 0 0 1 {} {} V{} A{tl} {tl} 0 0 1 {} {} Z",
+        number(x + top_left), number(y),
+        number(right - top_right), number(right), number(y + top_right),
+        number(bottom - bottom_right), number(right - bottom_right), number(bottom),
+        number(x + bottom_left), number(x), number(bottom - bottom_left),
+        number(y + top_left), number(x + top_left), number(y),
+        tr = number(top_right), br = number(bottom_right), bl = number(bottom_left), tl = number(top_left)
+    )
+}
+
+fn fill(color: ObjectColor) -> String {
+    if color.3 == 255 {
+        format!(r#"fill="rgb({},{},{})""#, color.0, color.1, color.2)
+    } else {
+        format!(r#"fill="rgb({},{},{})" fill-opacity="{}""#, color.0, color.1, color.2, number(color.3 as f32 / 255.0))
+    }
+}
@@ -0,0 +1,226 @@
+use crate::catplush_main::*;
+use std::fmt::Write;
+use glam::Vec2;
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct TerminalCell {
+    pub character: char,
+    /// `None` uses the terminal's own color.
+    pub foreground: Option<ObjectColor>,
+    /// `None` uses the terminal's own color.
+    pub background: Option<ObjectColor>
+}
+
+impl Default for TerminalCell {
+    fn default() -> Self {
+        TerminalCell { character: ' ', foreground: None, background: None }
+    }
+}
+
+/// Draws render commands onto a grid of characters. Every cell covers `cell_size` pixels of the
+/// layout, so laying out at `columns * cell_width` by `rows * cell_height` fills the whole grid.
+pub struct TerminalBackend {
+    columns: usize,
+    rows: usize,
+    cell_size: Vec2,
+    text_color: Option<ObjectColor>,
+
+    cells: Vec<TerminalCell>
+}
+
+impl TerminalBackend {
+    pub fn new(columns: usize, rows: usize, cell_width: f32, cell_height: f32) -> Self {
+        TerminalBackend {
+            columns,
+            rows,
+            cell_size: Vec2::new(cell_width, cell_height),
+            text_color: None,
+
+            cells: vec![TerminalCell::default(); columns * rows]
+        }
+    }
+
+    /// Bitmap fonts don't have a color of their own, so text uses the terminal's color unless this is set.
+    pub fn text_color(mut self, color: ObjectColor) -> Self {
+        self.text_color = Some(color);
+        self
+    }
+
+    /// The size in pixels to lay out at so the layout fills the grid.
+    pub fn layout_size(&self) -> (i32, i32) {
+        ((self.columns as f32 * self.cell_size.x) as i32, (self.rows as f32 * self.cell_size.y) as i32)
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.fill(TerminalCell::default());
+    }
+
+    /// The cell at a column and row, or `None` if it's outside of the grid.
+    pub fn cell(&self, column: usize, row: usize) -> Option<TerminalCell> {
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+        self.cells.get(row * self.columns + column).copied()
+    }
+
+    /// Just the characters, one line per row.
+    pub fn to_plain_string(&self) -> String {
+        let mut output = String::with_capacity((self.columns + 1) * self.rows);
+        if self.columns == 0 {
+            return output;
+        }
+        for row in self.cells.chunks(self.columns) {
+            output.extend(row.iter().map(|cell| cell.character));
+            output.push('\n');
+        }
+        output
+    }
+
+    /// The characters with 24-bit ANSI color escape codes, ready to be printed.
+    pub fn to_ansi_string(&self) -> String {
+        let mut output = String::new();
+        if self.columns == 0 {
+            return output;
+        }
+
+        for row in self.cells.chunks(self.columns) {
+            let mut foreground = None;
+            let mut background = None;
+
+            for cell in row {
+                if cell.foreground != foreground {
+                    match cell.foreground {
+                        Some(color) => { let _ = write!(output, "\x1b[38;2;{};{};{}m", color.0, color.1, color.2); },
+                        None => output.push_str("\x1b[39m")
+                    }
+                    foreground = cell.foreground;
+                }
+                if cell.background != background {
+                    match cell.background {
+                        Some(color) => { let _ = write!(output, "\x1b[48;2;{};{};{}m", color.0, color.1, color.2); },
+                        None => output.push_str("\x1b[49m")
+                    }
+                    background = cell.background;
+                }
+                output.push(cell.character);
+            }
+
+            output.push_str("\x1b[0m\n");
+        }
+
+        output
+    }
+
+    /// The range of cells a box covers, rounding to the nearest cell edge and cut down to the grid.
+    fn cell_range(&self, bounding_box: BoundingBox) -> (usize, usize, usize, usize) {
+        let to_column = |x: f32| ((x / self.cell_size.x).round().max(0.0) as usize).min(self.columns);
+        let to_row = |y: f32| ((y / self.cell_size.y).round().max(0.0) as usize).min(self.rows);
+
+        (
+            to_column(bounding_box.x),
+            to_row(bounding_box.y),
+            to_column(bounding_box.x + bounding_box.width),
+            to_row(bounding_box.y + bounding_box.height)
+        )
+    }
+
+    fn cell_mut(&mut self, column: usize, row: usize) -> Option<&mut TerminalCell> {
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+        self.cells.get_mut(row * self.columns + column)
+    }
+}
+
+impl RenderBackend for TerminalBackend {
+    /// Fills the cells with the background color and draws any borders with box-drawing characters,
+    /// with rounded corners if the rectangle has a corner radius.
+    fn draw_rectangle(&mut self, bounding_box: BoundingBox, data: &RectangleRenderData) {
+        let (min_column, min_row, max_column, max_row) = self.cell_range(bounding_box);
+        if min_column >= max_column || min_row >= max_row {
+            return;
+        }
+
+        if data.color.3 > 0 {
+            for row in min_row..max_row {
+                for column in min_column..max_column {
+                    let cell = &mut self.cells[row * self.columns + column];
+                    cell.background = Some(blend(cell.background, data.color));
+                    cell.character = ' ';
+                }
+            }
+        }
+
+        let border = data.border_width;
+        if data.stroke_color.3 == 0 || max_column - min_column < 2 || max_row - min_row < 2 {
+            return;
+        }
+
+        let (last_column, last_row) = (max_column - 1, max_row - 1);
+        let corner_radius = data.corner_radius;
+        let corners = [
+            (min_column, min_row, border.top > 0 && border.left > 0, if corner_radius.top_left > 0.0 { '╭' } else { '┌' }),
+            (last_column, min_row, border.top > 0 && border.right > 0, if corner_radius.top_right > 0.0 { '╮' } else { '┐' }),
+            (min_column, last_row, border.bottom > 0 && border.left > 0, if corner_radius.bottom_left > 0.0 { '╰' } else { '└' }),
+            (last_column, last_row, border.bottom > 0 && border.right > 0, if corner_radius.bottom_right > 0.0 { '╯' } else { '┘' })
+        ];
+
+        let mut set_border = |column: usize, row: usize, character: char| {
+            let cell = &mut self.cells[row * self.columns + column];
+            cell.character = character;
+            cell.foreground = Some(data.stroke_color);
+        };
+
+        for column in min_column..max_column {
+            if border.top > 0 { set_border(column, min_row, '─'); }
+            if border.bottom > 0 { set_border(column, last_row, '─'); }
+        }
+        for row in min_row..max_row {
+            if border.left > 0 { set_border(min_column, row, '│'); }
+            if border.right > 0 { set_border(last_column, row, '│'); }
+        }
+        for (column, row, has_corner, character) in corners {
+            if has_corner {
+                set_border(column, row, character);
+            }
+        }
+    }
+
+    /// Every character takes up one cell, starting from the cell the text element starts in.
+    fn draw_text(&mut self, bounding_box: BoundingBox, data: &TextRenderData) {
+        let line_advance = data.bitmap.cell_size.y * data.scale_factor() * data.line_height;
+        let column = (bounding_box.x / self.cell_size.x).round().max(0.0) as usize;
+        let text_color = self.text_color;
+
+        for (line_number, line) in data.lines().enumerate() {
+            let row = ((bounding_box.y + line_advance * line_number as f32) / self.cell_size.y).round().max(0.0) as usize;
+
+            for (i, character) in line.chars().enumerate() {
+                if let Some(cell) = self.cell_mut(column + i, row) {
+                    cell.character = character;
+                    cell.foreground = text_color;
+                }
+            }
+        }
+    }
+
+    /// There's no way to show pixels, so images are filled in with a shade character.
+    fn draw_image(&mut self, _bounding_box: BoundingBox, data: &TextureRenderData) {
+        let (min_column, min_row, max_column, max_row) = self.cell_range(data.draw_box);
+
+        for row in min_row..max_row {
+            for column in min_column..max_column {
+                self.cells[row * self.columns + column].character = '▒';
+            }
+        }
+    }
+}
+
+/// Blends a color over a cell's background, treating the terminal's own background as black.
+fn blend(background: Option<ObjectColor>, color: ObjectColor) -> ObjectColor {
+    let background = background.unwrap_or(ObjectColor::BLACK);
+    let alpha = color.3 as f32 / 255.0;
+    let mix = |under: u8, over: u8| (under as f32 + (over as f32 - under as f32) * alpha).round() as u8;
+
+    ObjectColor(mix(background.0, color.0), mix(background.1, color.1), mix(background.2, color.2), 255)
+}
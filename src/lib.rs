@@ -1,5 +1,5 @@
 pub mod catplush_main {
-    use std::{cmp::Ordering, num::{NonZeroU32}};
+    use std::{any::Any, borrow::{Borrow, Cow}, cmp::Ordering, collections::{HashMap, HashSet}, error::Error, hash::{DefaultHasher, Hash, Hasher}, fmt, num::{NonZeroU32}, ops::{Deref, DerefMut}, panic::Location, sync::{atomic::{self, AtomicU32}, Arc, LazyLock, Mutex}};
     use glam::{Vec4, Vec2};
 
     /////////////////////////////////////////////////////////////////
@@ -7,28 +7,121 @@ pub mod catplush_main {
     /////////////////////////////////////////////////////////////////
 
     // Holds all of the layout information and currently opened elements for building the ui
-    // heirarchy
-    pub struct CatplushContext {
+    // heirarchy. The lifetime is for the `StateStore` passed to `begin_layout_with_state`.
+    pub struct CatplushContext<'s> {
         layout_elements: Vec<Node>,
 
-        open_layout_elements: Vec<usize>
+        open_layout_elements: Vec<usize>,
+        /// Problems found while building that can't be worked out from the tree later, like extra closes
+        build_issues: Vec<LayoutIssue>,
+
+        /// `None` until `set_theme` is called, so the default theme isn't rebuilt every frame
+        theme: Option<Theme>,
+        state: ElementState<'s>,
+        debug_view: Option<DebugView>,
+        /// Added with `add_font` and kept through `reset`
+        fonts: Vec<Font>,
+        /// Put in every `FontHandle` this context makes, so handles from other contexts can be caught
+        id: u32,
+
+        /// Scratch space for the sizing passes, kept so it doesn't need allocating every frame
+        growable_elements: Vec<usize>,
+        /// The nodes the layout passes are inside of, with the children they haven't been through yet
+        traversal_stack: Vec<(usize, ChildCursor)>,
+
+        layout_cache: HashMap<ElementId, CachedLayout>,
+        layout_caching: bool,
+        /// Scratch space for restoring cached layouts, the cached nodes being read from and how far into them
+        cache_cursors: Vec<(usize, usize)>,
+        /// Counts `reset`s, for throwing out cached layouts of elements that are gone
+        frame: u64
     }
 
-    impl CatplushContext {
+    static NEXT_CONTEXT_ID: AtomicU32 = AtomicU32::new(0);
+
+    impl<'s> CatplushContext<'s> {
+        /// Makes a context to keep around between frames. Call `reset` at the start of every frame,
+        /// which keeps all of the memory from the last one so a steady ui doesn't allocate anything.
+        /// Element state is kept inside the context.
+        pub fn new() -> Self {
+            Self::with_state(ElementState::Owned(StateStore::new()))
+        }
+
+        /// Starts a new frame with a fresh context. Anything stored with `state` is thrown away at the
+        /// end of it, use `begin_layout_with_state` (or `new` and `reset`) to keep it around.
+        #[track_caller]
         pub fn begin_layout(window_size: (i32, i32), layout_direction: ChildLayoutDirection) -> Self {
-            let mut new_context = CatplushContext {
+            let mut new_context = Self::new();
+            // There's no next frame to use the cache in
+            new_context.layout_caching = false;
+            new_context.reset(window_size, layout_direction);
+            new_context
+        }
+
+        /// Starts a new frame that keeps element state in `state` between frames. Entries for elements
+        /// that aren't opened (or asked for with `state`) this frame are removed in `end_layout`.
+        #[track_caller]
+        pub fn begin_layout_with_state(window_size: (i32, i32), layout_direction: ChildLayoutDirection, state: &'s mut StateStore) -> Self {
+            let mut new_context = Self::with_state(ElementState::Stored(state));
+            new_context.layout_caching = false;
+            new_context.reset(window_size, layout_direction);
+            new_context
+        }
+
+        fn with_state(state: ElementState<'s>) -> Self {
+            CatplushContext {
                 layout_elements: vec![],
-                open_layout_elements: vec![]
-            };
+                open_layout_elements: vec![],
+                build_issues: vec![],
+                theme: None,
+                state,
+                debug_view: None,
+                fonts: vec![],
+                id: NEXT_CONTEXT_ID.fetch_add(1, atomic::Ordering::Relaxed),
+                growable_elements: vec![],
+                traversal_stack: vec![],
+                layout_cache: HashMap::new(),
+                layout_caching: true,
+                cache_cursors: vec![],
+                frame: 0
+            }
+        }
+    }
+
+    impl Default for CatplushContext<'_> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl CatplushContext<'_> {
+        /// Clears out the last frame and opens the root element for a new one. The theme is kept.
+        #[track_caller]
+        pub fn reset(&mut self, window_size: (i32, i32), layout_direction: ChildLayoutDirection) {
+            self.layout_elements.clear();
+            self.open_layout_elements.clear();
+            self.build_issues.clear();
+            self.debug_view = None;
+            self.state.store().frame += 1;
+            self.frame += 1;
 
-            new_context.open_element(UiElement::new()
+            self.open_element(UiElement::new()
                 .sizing(SizingMode::Fixed(window_size.0), SizingMode::Fixed(window_size.1))
                 .layout_direction(layout_direction));
 
-            new_context.layout_elements[0].element.final_size_x = window_size.0 as f32;
-            new_context.layout_elements[0].element.final_size_y = window_size.1 as f32;
+            self.layout_elements[0].element.final_size_x = window_size.0 as f32;
+            self.layout_elements[0].element.final_size_y = window_size.1 as f32;
+        }
 
-            new_context
+        /// Swaps the theme widgets read from. The context starts with `Theme::dark`, and keeps the theme
+        /// through `reset` so it only needs setting when it changes.
+        pub fn set_theme(&mut self, theme: &Theme) {
+            self.theme = Some(theme.clone());
+        }
+
+        pub fn theme(&self) -> &Theme {
+            static DEFAULT_THEME: LazyLock<Theme> = LazyLock::new(Theme::dark);
+            self.theme.as_ref().unwrap_or(&DEFAULT_THEME)
         }
 
         pub fn get_all_elements(&mut self) -> Vec<&UiElement> {
@@ -41,18 +134,74 @@ pub mod catplush_main {
     }
 
 
+    /// Closes its element when dropped. Made by `CatplushContext::open_scope`.
+    pub struct ElementGuard<'a, 's> {
+        context: &'a mut CatplushContext<'s>
+    }
+
+    impl<'s> Deref for ElementGuard<'_, 's> {
+        type Target = CatplushContext<'s>;
+
+        fn deref(&self) -> &CatplushContext<'s> {
+            self.context
+        }
+    }
+
+    impl<'s> DerefMut for ElementGuard<'_, 's> {
+        fn deref_mut(&mut self) -> &mut CatplushContext<'s> {
+            self.context
+        }
+    }
+
+    impl Drop for ElementGuard<'_, '_> {
+        fn drop(&mut self) {
+            self.context.close_element();
+        }
+    }
+
+    /// Steps through a node's children by index, so the nodes can be changed in between steps.
+    struct ChildCursor {
+        next: Option<usize>
+    }
+
+    impl ChildCursor {
+        fn step(&mut self, nodes: &[Node]) -> Option<usize> {
+            let current = self.next?;
+            self.next = nodes[current].next_sibling;
+            Some(current)
+        }
+    }
+
     #[derive(Default)]
     struct Node {
         parent: Option<usize>,
         element: UiElement,
-        child_elements: Vec<usize>
+        /// Children are linked through their siblings instead of each node having its own list, so
+        /// building the tree doesn't allocate once `layout_elements` is big enough
+        first_child: Option<usize>,
+        last_child: Option<usize>,
+        next_sibling: Option<usize>,
+        child_count: usize,
+        /// Where `open_element` was called from, for pointing at the element in warnings
+        location: Option<&'static Location<'static>>,
+
+        /// One past the last node inside this one, since a node's children all come right after it.
+        /// Zero until it's closed.
+        subtree_end: usize,
+        /// Hash of everything that affects the layout of this node and the ones inside it
+        subtree_hash: u64,
+        /// Sizes after the first sizing pass, which is how much room the node asks its parent for
+        initial_size_x: f32,
+        initial_size_y: f32,
+        cache: CacheState
     }
 
     ///////////////////////////////////////////////////////
     //////////////// Element Structures ///////////////////
     ///////////////////////////////////////////////////////
 
-    #[derive(Default, Copy, Clone)]
+    #[derive(Default, Copy, Clone, PartialEq, Eq, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct ObjectColor( pub u8, pub u8, pub u8, pub u8 );
 
     impl ObjectColor {
@@ -75,19 +224,22 @@ pub mod catplush_main {
         pub const BLUE:  Self = ObjectColor(0  , 0  , 255, 255);
     }
 
-    #[derive(PartialEq)]
+    #[derive(Copy, Clone, PartialEq, Hash, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub enum ChildLayoutDirection {
         LeftToRight,
         TopToBottom,
     }
 
-    #[derive(Clone, Copy, PartialEq)]
+    #[derive(Clone, Copy, PartialEq, Hash, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub enum SizingMode {
         Fixed(i32),
         Fit,
         Grow
     }
 
+    #[derive(Clone, Hash)]
     pub struct Sizing {
         pub width: SizingMode,
         pub height: SizingMode
@@ -103,9 +255,11 @@ pub mod catplush_main {
         }
     }
 
+    #[derive(Copy, Clone, PartialEq, Hash, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct SizeLimit {
-        min: i32,
-        max: i32
+        pub min: i32,
+        pub max: i32
     }
 
     impl Default for SizeLimit {
@@ -114,13 +268,14 @@ pub mod catplush_main {
         }
     }
 
-    #[derive(Default)]
+    #[derive(Default, Clone, Hash)]
     pub struct SizingLimits {
         width: SizeLimit,
         height: SizeLimit
     }
 
-    #[derive(Default, Copy, Clone)]
+    #[derive(Default, Copy, Clone, PartialEq, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct CornerRadius {
         pub top_right: f32,
         pub bottom_right: f32,
@@ -142,7 +297,8 @@ pub mod catplush_main {
         }
     }
 
-    #[derive(Default, Copy, Clone)]
+    #[derive(Default, Copy, Clone, PartialEq, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct BorderWidth {
         pub top: i32,
         pub right: i32,
@@ -164,11 +320,33 @@ pub mod catplush_main {
         }
     }
 
+    /// How far in from each edge of a nine-slice texture the slices are cut, in pixels.
+    #[derive(Default, Copy, Clone, PartialEq, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct NineSliceInsets {
+        pub top: i32,
+        pub right: i32,
+        pub bottom: i32,
+        pub left: i32
+    }
+
+    impl NineSliceInsets {
+        pub fn new(top: i32, right: i32, bottom: i32, left: i32) -> Self {
+            NineSliceInsets {top, right, bottom, left}
+        }
+
+        pub fn all(inset: i32) -> Self {
+            NineSliceInsets {top: inset, right: inset, bottom: inset, left: inset}
+        }
+    }
+
+    #[derive(Copy, Clone, PartialEq, Hash, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Padding {
-        left: i32,
-        right: i32,
-        top: i32,
-        bottom: i32,
+        pub left: i32,
+        pub right: i32,
+        pub top: i32,
+        pub bottom: i32,
     }
 
     impl Padding {
@@ -185,7 +363,8 @@ pub mod catplush_main {
         }
     }
 
-    #[derive(Default, PartialEq)]
+    #[derive(Default, Copy, Clone, PartialEq, Hash, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub enum ChildXAlignment {
         #[default]
         Left,
@@ -193,7 +372,8 @@ pub mod catplush_main {
         Right
     }
 
-    #[derive(Default, PartialEq)]
+    #[derive(Default, Copy, Clone, PartialEq, Hash, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub enum ChildYAlignment {
         #[default]
         Top,
@@ -201,7 +381,7 @@ pub mod catplush_main {
         Bottom
     }
 
-    #[derive(Default)]
+    #[derive(Default, Clone, Hash)]
     pub struct ChildAlignment {
         pub x: ChildXAlignment,
         pub y: ChildYAlignment,
@@ -213,6 +393,7 @@ pub mod catplush_main {
         }
     }
 
+    #[derive(Clone, Hash)]
     pub struct LayoutConfig {
         pub(crate) sizing: Sizing,
         pub(crate) size_constraints: SizingLimits,
@@ -246,12 +427,158 @@ pub mod catplush_main {
     #[derive(Copy, Clone)]
     pub struct CatplushTextureData {
         pub texture_id: NonZeroU32,
+        /// Width of the part of the texture being used, in pixels.
         pub width: i32,
+        /// Height of the part of the texture being used, in pixels.
         pub height: i32,
+        /// Where the part of the texture being used starts, from 0 to 1.
+        pub uv_pos: Vec2,
+        /// How much of the texture is being used, from 0 to 1.
+        pub uv_size: Vec2,
+    }
+
+    impl CatplushTextureData {
+        /// Describes a whole texture.
+        pub fn new(texture_id: NonZeroU32, width: i32, height: i32) -> Self {
+            CatplushTextureData { texture_id, width, height, uv_pos: Vec2::ZERO, uv_size: Vec2::ONE }
+        }
+
+        /// Returns a rectangle inside of this texture. The position and size are in pixels and are
+        /// relative to this texture's top left corner.
+        pub fn sub_texture(&self, x: i32, y: i32, width: i32, height: i32) -> Self {
+            let pixel_to_uv = self.uv_size / Vec2::new(self.width as f32, self.height as f32);
+
+            CatplushTextureData {
+                texture_id: self.texture_id,
+                width,
+                height,
+                uv_pos: self.uv_pos + Vec2::new(x as f32, y as f32) * pixel_to_uv,
+                uv_size: Vec2::new(width as f32, height as f32) * pixel_to_uv
+            }
+        }
+    }
+
+    /// A texture split up into frames, either on a grid or as named rectangles.
+    pub struct SpriteSheet {
+        texture: CatplushTextureData,
+        frames: Vec<CatplushTextureData>,
+        frame_names: HashMap<String, usize>
+    }
+
+    impl SpriteSheet {
+        /// Returns a sprite sheet with no frames. Add frames to it with `with_frame`.
+        pub fn new(texture: CatplushTextureData) -> Self {
+            SpriteSheet { texture, frames: vec![], frame_names: HashMap::new() }
+        }
+
+        /// Splits the texture into cells of the same size. Frames are numbered left to right, then top to bottom,
+        /// and cells that don't fully fit are left out. Panics if the cell size isn't positive.
+        pub fn grid(texture: CatplushTextureData, cell_width: i32, cell_height: i32) -> Self {
+            assert!(cell_width > 0 && cell_height > 0, "sprite sheet cells have to be at least 1x1, got {cell_width}x{cell_height}");
+            let mut sprite_sheet = SpriteSheet::new(texture);
+
+            for row in 0..(texture.height / cell_height) {
+                for column in 0..(texture.width / cell_width) {
+                    sprite_sheet.frames.push(texture.sub_texture(column * cell_width, row * cell_height, cell_width, cell_height));
+                }
+            }
+
+            sprite_sheet
+        }
+
+        /// Adds a named frame. The position and size are in pixels.
+        pub fn with_frame(mut self, name: &str, x: i32, y: i32, width: i32, height: i32) -> Self {
+            self.frames.push(self.texture.sub_texture(x, y, width, height));
+            self.frame_names.insert(name.to_owned(), self.frames.len() - 1);
+            self
+        }
+
+        /// Gives a name to a frame that already exists, like one made by `grid`.
+        pub fn name_frame(mut self, name: &str, index: usize) -> Self {
+            self.frame_names.insert(name.to_owned(), index);
+            self
+        }
+
+        pub fn frame(&self, index: usize) -> Option<CatplushTextureData> {
+            self.frames.get(index).copied()
+        }
+
+        pub fn named(&self, name: &str) -> Option<CatplushTextureData> {
+            self.frame(*self.frame_names.get(name)?)
+        }
+
+        pub fn frame_count(&self) -> usize {
+            self.frames.len()
+        }
+
+        pub fn texture(&self) -> CatplushTextureData {
+            self.texture
+        }
+    }
+
+    /// How an image is drawn inside its element when the element is not the same size as the image.
+    #[derive(Default, Copy, Clone, PartialEq, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub enum ImageFit {
+        /// Stretches the image to fill the element, ignoring its aspect ratio.
+        #[default]
+        Fill,
+        /// Scales the image to fit inside the element while keeping its aspect ratio. The leftover
+        /// space is left empty.
+        Contain,
+        /// Scales the image to cover the whole element while keeping its aspect ratio. Whatever
+        /// doesn't fit is cropped off.
+        Cover,
+        /// Draws the image at its native size in the center of the element, cropped if it doesn't fit.
+        None
+    }
+
+    impl ImageFit {
+        /// Returns the box the image should be drawn in along with the uv position and size of the
+        /// part of the texture that should be shown.
+        pub(crate) fn apply(&self, texture_size: Vec2, bounding_box: BoundingBox) -> (BoundingBox, Vec2, Vec2) {
+            let box_size = Vec2::new(bounding_box.width, bounding_box.height);
+
+            // Nothing would be visible, and the scales below would divide by zero
+            if texture_size.x <= 0.0 || texture_size.y <= 0.0 || box_size.x <= 0.0 || box_size.y <= 0.0 {
+                return (bounding_box, Vec2::ZERO, Vec2::ONE);
+            }
+
+            match self {
+                ImageFit::Fill => (bounding_box, Vec2::ZERO, Vec2::ONE),
+                ImageFit::Contain => {
+                    let scale = f32::min(box_size.x / texture_size.x, box_size.y / texture_size.y);
+                    let draw_size = texture_size * scale;
+                    let offset = (box_size - draw_size) / 2.0;
+
+                    (BoundingBox { x: bounding_box.x + offset.x, y: bounding_box.y + offset.y, width: draw_size.x, height: draw_size.y }, Vec2::ZERO, Vec2::ONE)
+                },
+                ImageFit::Cover => {
+                    let scale = f32::max(box_size.x / texture_size.x, box_size.y / texture_size.y);
+                    let uv_size = (box_size / scale / texture_size).min(Vec2::ONE);
+
+                    (bounding_box, (Vec2::ONE - uv_size) / 2.0, uv_size)
+                },
+                ImageFit::None => {
+                    let draw_size = texture_size.min(box_size);
+                    let offset = (box_size - draw_size) / 2.0;
+                    let uv_size = draw_size / texture_size;
+
+                    (BoundingBox { x: bounding_box.x + offset.x, y: bounding_box.y + offset.y, width: draw_size.x, height: draw_size.y }, (Vec2::ONE - uv_size) / 2.0, uv_size)
+                }
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct CatplushImageData {
+        pub(crate) texture: CatplushTextureData,
+        pub(crate) fit: ImageFit
     }
 
+    #[derive(Clone)]
     pub struct CatplushTextData {
-        pub(crate) bitmap: BitmapConfiguration,
+        pub(crate) font: TextFont,
         pub(crate) text: String,
         pub(crate) font_size: u32,
         pub(crate) line_height: f32,
@@ -259,19 +586,28 @@ pub mod catplush_main {
         pub(crate) break_on_overflow: bool
     }
 
-    #[derive(Default)]
+    #[derive(Clone)]
+    pub struct CatplushNineSliceData {
+        pub(crate) texture: CatplushTextureData,
+        pub(crate) insets: NineSliceInsets
+    }
+
+    #[derive(Default, Clone)]
     pub enum ObjectType {
         #[default]
         Unset,
         Rectangle,
         Text ( CatplushTextData ),
-        Image ( CatplushTextureData )
+        Image ( CatplushImageData ),
+        NineSlice ( CatplushNineSliceData )
     }
 
-    #[derive(Default)]
+    #[derive(Default, Clone)]
     pub struct UiElement {
         pub object_type: ObjectType,
         pub id: Option<&'static str>,
+        /// Hash of the id (and index), for looking up the element's state
+        pub(crate) element_id: Option<ElementId>,
         pub layout: LayoutConfig,
 
         pub color: ObjectColor,
@@ -305,6 +641,15 @@ pub mod catplush_main {
             self
         }
 
+        /// Draws the texture as the element's background. The corners keep their size, the edges are
+        /// stretched along one axis and the middle is stretched to fill the rest of the element.
+        pub fn nine_slice(mut self, texture: CatplushTextureData, insets: NineSliceInsets) -> Self {
+            self.object_type = ObjectType::NineSlice(CatplushNineSliceData { texture, insets });
+            self
+        }
+
+        /// Sets the element's size to the texture's size, or scales it to the given width or height.
+        /// Use `image_fit` along with `sizing` to let the element decide the size of the image instead.
         pub fn image(mut self, texture: CatplushTextureData, width: Option<i32>, height: Option<i32>, ignore_aspect_ratio: bool) -> Self {
             let width_to_height_ratio = texture.width as f32 / texture.height as f32;
             let height_to_width_ratio = texture.height as f32 / texture.width as f32;
@@ -319,11 +664,11 @@ pub mod catplush_main {
                 match (width, height) {
                     (Some(amount), Some(_)) | (Some(amount), None) => {
                         actual_width = amount;
-                        actual_height = (actual_width as f32 * width_to_height_ratio) as i32;
+                        actual_height = (actual_width as f32 * height_to_width_ratio) as i32;
                     },
                     (None, Some(amount)) => {
                         actual_height = amount;
-                        actual_width = (actual_height as f32 * height_to_width_ratio) as i32;
+                        actual_width = (actual_height as f32 * width_to_height_ratio) as i32;
                     },
                     (None, None) => {
                         actual_width = texture.width;
@@ -332,48 +677,73 @@ pub mod catplush_main {
                 }
             }
 
-            self.object_type = ObjectType::Image(CatplushTextureData {
-                texture_id: texture.texture_id,
-                width: actual_width,
-                height: actual_height
+            self.object_type = ObjectType::Image(CatplushImageData {
+                texture,
+                fit: ImageFit::default()
             });
 
             self.layout.sizing = Sizing { width: SizingMode::Fixed(actual_width), height: SizingMode::Fixed(actual_height) };
             self
         }
 
-        /// Line height is a multiplier to the font height, with the normal font height being at 1.0
-        pub fn text(mut self, bitmap: &BitmapConfiguration, text_slice: &str, font_size: u32, line_height: f32) -> Self {
-            let font_size_factor = font_size as f32 / bitmap.cell_size.y;
-            let mut text = text_slice.to_owned();
+        /// Only works if used after configuring as an image element.
+        pub fn image_fit(mut self, fit: ImageFit) -> Self {
+            if let ObjectType::Image(ref mut image) = self.object_type {
+                image.fit = fit;
+            }
 
-            let mut new_lines: Vec<u32> = text.match_indices("\n").map(|x| x.0 as u32).collect();
+            self
+        }
 
-            text = text.replace("\n", "");
+        /// Line height is a multiplier to the font height, with the normal font height being at 1.0.
+        /// This copies the whole bitmap configuration into a new `Arc` for every element, so for lots of labels use
+        /// `shared_text` with one `Arc` made ahead of time, or `font_text`.
+        pub fn text(self, bitmap: &BitmapConfiguration, text_slice: &str, font_size: u32, line_height: f32) -> Self {
+            self.shared_text(&Arc::new(bitmap.clone()), text_slice, font_size, line_height)
+        }
 
-            // Correct for the new line characters being removed
-            for (index, new_line) in new_lines.iter_mut().enumerate() {
-                *new_line -= index as u32;
+        /// Same as `text`, but shares the bitmap configuration with every other element made from it.
+        pub fn shared_text(self, bitmap: &Arc<BitmapConfiguration>, text_slice: &str, font_size: u32, line_height: f32) -> Self {
+            for char in text_slice.chars() {
+                if char != '\n' && !bitmap.character_list.contains(char) {
+                    panic!("Character {} in the string \"{}\" is not in the bitmap you provided, or the list of characters is missing some that are in the bitmap.", char, text_slice);
+                }
             }
 
-            for char in text.chars() {
-                if bitmap.character_list.find(char).is_none() {
-                    panic!("Character {} in the string \"{}\" is not in the bitmap you provided, or the list of characters is missing some that are in the bitmap.", char, text);
+            self.text_object(TextFont::Bitmap(Arc::clone(bitmap)), bitmap.cell_size, text_slice, font_size, line_height)
+        }
+
+        /// Same as `text`, but with a font from `CatplushContext::add_font`, so the element only holds a
+        /// handle to it. Characters that aren't in the font are left out and show up in `validate`.
+        pub fn font_text(self, font: FontHandle, text_slice: &str, font_size: u32, line_height: f32) -> Self {
+            self.text_object(TextFont::Handle(font), font.cell_size, text_slice, font_size, line_height)
+        }
+
+        fn text_object(mut self, font: TextFont, cell_size: Vec2, text_slice: &str, font_size: u32, line_height: f32) -> Self {
+            let font_size_factor = font_size as f32 / cell_size.y;
+
+            // The new line characters are taken out, and the lines are split where they were instead
+            let mut text = String::with_capacity(text_slice.len());
+            let mut new_lines: Vec<u32> = vec![];
+            for (index, line) in text_slice.split('\n').enumerate() {
+                if index > 0 {
+                    new_lines.push(text.len() as u32);
                 }
+                text.push_str(line);
             }
 
             let longest_line = if !new_lines.is_empty() { find_largest_split(&new_lines, text.len() as u32) } else { text.len() as u32 };
 
-            let width = (bitmap.cell_size.x * font_size_factor * longest_line as f32) as i32;
-            let height = (bitmap.cell_size.y * (new_lines.len() as f32 + 1.0) * line_height * font_size_factor) as i32;
+            let width = (cell_size.x * font_size_factor * longest_line as f32) as i32;
+            let height = (cell_size.y * (new_lines.len() as f32 + 1.0) * line_height * font_size_factor) as i32;
 
             self.layout.sizing = Sizing { width: SizingMode::Fixed(width), height: SizingMode::Fixed(height) };
 
             self = self.limit_width(0, width);
 
             self.object_type = ObjectType::Text(CatplushTextData {
-                bitmap: bitmap.clone(),
-                text: text.to_string(),
+                font,
+                text,
                 font_size,
                 line_height,
                 split_indices: new_lines,
@@ -441,164 +811,863 @@ pub mod catplush_main {
             self
         }
 
-        // Commenting this out until I figure out how to do IDs
-        // pub fn id(mut self, id: &'static str) -> Self {
-        //     self.id = Some(id);
-        //     self
-        // }
-    }
-
-    pub fn split_multiple_indices<'a>(text: &'a str, indices_to_split: &[u32]) -> Vec<&'a str> {
-        if indices_to_split.is_empty() {
-            return vec![text]
+        /// Names the element. The name shows up in render commands and warnings, and `ElementId::new`
+        /// with the same name gets to the element's state.
+        pub fn id(mut self, id: &'static str) -> Self {
+            self.id = Some(id);
+            self.element_id = Some(ElementId::new(id));
+            self
         }
 
-        let mut segments: Vec<&str> = vec![];
-        let mut temporary_split: (&str, &str);
-        let mut current_split: &str = text;
+        /// For elements made in a loop, which all share a name. Matches `ElementId::indexed`.
+        pub fn id_indexed(mut self, id: &'static str, index: u32) -> Self {
+            self.id = Some(id);
+            self.element_id = Some(ElementId::indexed(id, index));
+            self
+        }
 
-        for (index, split) in indices_to_split.iter().enumerate() {
-            if index == 0 {
-                temporary_split = current_split.split_at(*split as usize);
-            } else {
-                temporary_split = current_split.split_at((*split - indices_to_split[index - 1]) as usize);
+        /// Applies everything the style sets. A color turns the element into a rectangle, so use
+        /// `styled_text` for text elements.
+        pub fn style(mut self, style: &Style) -> Self {
+            if let Some(color) = style.color {
+                self = self.rectangle(color, style.corner_radius.unwrap_or_default());
             }
-
-            segments.push(temporary_split.0);
-            if index == indices_to_split.len() - 1 {
-                segments.push(temporary_split.1)
+            if let Some((stroke_color, border_width)) = style.border {
+                self = self.border(stroke_color, border_width);
             }
-            current_split = temporary_split.1;
+            if let Some(padding) = style.padding {
+                self = self.padding(padding);
+            }
+            if let Some(child_gap) = style.child_gap {
+                self = self.child_gap(child_gap);
+            }
+            if let Some((x_align, y_align)) = style.alignment {
+                self = self.alignment(x_align, y_align);
+            }
+            self
         }
 
-        segments
+        /// Makes a text element with the style's font settings. If the style doesn't have any, the
+        /// element is left as it is and a warning is logged.
+        pub fn styled_text(self, style: &Style, text: &str) -> Self {
+            let Some(text_style) = &style.text else {
+                log::warn!("The style used for the text \"{text}\" doesn't have a font, leaving it out");
+                return self;
+            };
+            self.shared_text(&text_style.bitmap, text, text_style.font_size, text_style.line_height)
+        }
     }
 
-    pub fn find_largest_split(splits: &[u32], text_length: u32) -> u32 {
-        let mut largest_split = 0;
-        let all_splits: Vec<u32> = [&[0], splits, &[text_length]].concat();
+    ///////////////////////////////////////////////////////
+    //////////////// Styles and Themes ////////////////////
+    ///////////////////////////////////////////////////////
 
-        for (i, _) in all_splits.iter().enumerate() {
-            if i < all_splits.len() - 1 {
-                largest_split = u32::max(largest_split, u32::abs_diff(all_splits[i], all_splits[i+1]));
-            }
-        }
+    #[derive(Clone)]
+    pub struct TextStyle {
+        /// Shared with every text element made from the style, so they don't each copy the bitmap
+        pub bitmap: Arc<BitmapConfiguration>,
+        pub font_size: u32,
+        pub line_height: f32
+    }
 
-        largest_split
+    /// A set of element settings that can be reused instead of repeating them at every call site.
+    /// Anything left as `None` isn't touched when the style is applied.
+    #[derive(Clone, Default)]
+    pub struct Style {
+        pub color: Option<ObjectColor>,
+        pub corner_radius: Option<CornerRadius>,
+        pub border: Option<(ObjectColor, BorderWidth)>,
+        pub padding: Option<Padding>,
+        pub child_gap: Option<i32>,
+        pub alignment: Option<(ChildXAlignment, ChildYAlignment)>,
+        pub text: Option<TextStyle>
     }
 
-    impl CatplushContext {
-        //////////// Layout Building Functions //////////////
-        pub fn open_element(&mut self, element: UiElement) {
-            let new_element_index = self.layout_elements.len();
-            let mut parent_index: Option<usize> = None;
+    impl Style {
+        pub fn new() -> Self {
+            Self::default()
+        }
 
-            if !self.open_layout_elements.is_empty() {
-                self.layout_elements[*self.open_layout_elements.last_mut().unwrap()].child_elements.push(new_element_index);
-                parent_index = Some(*self.open_layout_elements.last().unwrap());
-            }
+        pub fn rectangle(mut self, color: ObjectColor, corner_radius: CornerRadius) -> Self {
+            self.color = Some(color);
+            self.corner_radius = Some(corner_radius);
+            self
+        }
 
-            self.open_layout_elements.push(new_element_index);
-            self.layout_elements.push(Node {
-                parent: parent_index,
-                element,
-                child_elements: vec![]
-            });
+        pub fn border(mut self, stroke_color: ObjectColor, border_width: BorderWidth) -> Self {
+            self.border = Some((stroke_color, border_width));
+            self
         }
 
-        pub fn close_element(&mut self) {
-            if self.open_layout_elements.len() <= 1 {
-                return
-            }
-            self.open_layout_elements.pop();
+        pub fn padding(mut self, padding: Padding) -> Self {
+            self.padding = Some(padding);
+            self
         }
 
-        pub(crate) fn size_all(&mut self) {
-            self.initial_sizing_along_axis(true, 0);
-            self.size_along_axis(true, 0);
-            self.wrap_text(0);
-            self.initial_sizing_along_axis(false, 0);
-            self.size_along_axis(false, 0);
+        pub fn child_gap(mut self, amount: i32) -> Self {
+            self.child_gap = Some(amount);
+            self
         }
 
-        pub(crate) fn initial_sizing_along_axis(&mut self, left_to_right: bool, current_index: usize) {
-            for child_index in self.layout_elements[current_index].child_elements.clone() {
-                self.initial_sizing_along_axis(left_to_right, child_index);
-            }
+        pub fn alignment(mut self, x_align: ChildXAlignment, y_align: ChildYAlignment) -> Self {
+            self.alignment = Some((x_align, y_align));
+            self
+        }
 
-            if self.layout_elements[current_index].parent.is_none() {
-                return;
+        /// Line height is a multiplier to the font height, the same as in `UiElement::text`.
+        pub fn font(mut self, bitmap: &BitmapConfiguration, font_size: u32, line_height: f32) -> Self {
+            self.text = Some(TextStyle { bitmap: Arc::new(bitmap.clone()), font_size, line_height });
+            self
+        }
+
+        /// Fills in everything this style doesn't set with the parent's settings.
+        pub fn inherit(self, parent: &Style) -> Self {
+            Style {
+                color: self.color.or(parent.color),
+                corner_radius: self.corner_radius.or(parent.corner_radius),
+                border: self.border.or(parent.border),
+                padding: self.padding.or(parent.padding),
+                child_gap: self.child_gap.or(parent.child_gap),
+                alignment: self.alignment.or(parent.alignment),
+                text: self.text.or_else(|| parent.text.clone())
             }
+        }
+    }
 
-            let parent_index = self.layout_elements[current_index].parent.unwrap();
-            let [current_node, parent_node] = self.layout_elements.get_disjoint_mut([current_index, parent_index]).unwrap();
+    /// Named styles for a whole ui, so widgets can ask for "button" instead of picking colors
+    /// themselves. Set it on the context with `set_theme` and read it back with `theme`.
+    #[derive(Clone)]
+    pub struct Theme {
+        /// For clearing the screen behind the ui
+        pub background: ObjectColor,
+        styles: HashMap<String, Style>,
+        /// Names that were asked for but aren't in the theme, so each one is only warned about once
+        missing_styles: Arc<Mutex<HashSet<String>>>
+    }
 
-            let child_gap = (current_node.child_elements.len() as i32 - 1) * current_node.element.layout.child_gap;
+    impl Default for Theme {
+        fn default() -> Self {
+            Theme::dark()
+        }
+    }
 
-            // Padding
-            if left_to_right {
-                current_node.element.final_size_x += (current_node.element.layout.padding.left + current_node.element.layout.padding.right) as f32;
+    impl Theme {
+        /// A theme without any styles in it.
+        pub fn new(background: ObjectColor) -> Self {
+            Theme { background, styles: HashMap::new(), missing_styles: Arc::default() }
+        }
 
-                if current_node.element.layout.layout_direction == ChildLayoutDirection::LeftToRight {
-                    current_node.element.final_size_x += child_gap as f32;
-                }
+        pub fn with_style(mut self, name: &str, style: Style) -> Self {
+            self.set_style(name, style);
+            self
+        }
 
-                match current_node.element.layout.sizing.width {
-                    SizingMode::Fixed(size) => {current_node.element.final_size_x = size as f32},
-                    SizingMode::Fit => {},
-                    SizingMode::Grow => {},
-                }
+        pub fn set_style(&mut self, name: &str, style: Style) {
+            self.styles.insert(name.to_string(), style);
+        }
 
-                current_node.element.final_size_x = f32::max(current_node.element.final_size_x, current_node.element.layout.size_constraints.width.min as f32);
+        /// Returns an empty style if there isn't one with this name. A warning is logged the first time each
+        /// missing name is asked for.
+        pub fn style(&self, name: &str) -> &Style {
+            static EMPTY_STYLE: Style = Style {
+                color: None, corner_radius: None, border: None, padding: None, child_gap: None, alignment: None, text: None
+            };
 
-                if parent_node.element.layout.sizing.width == SizingMode::Fit || parent_node.element.layout.sizing.width == SizingMode::Grow {
-                    if parent_node.element.layout.layout_direction == ChildLayoutDirection::LeftToRight {
-                        parent_node.element.final_size_x += current_node.element.final_size_x;
-                    } else {
-                        parent_node.element.final_size_x = f32::max(current_node.element.final_size_x, parent_node.element.final_size_x)
+            match self.styles.get(name) {
+                Some(style) => style,
+                None => {
+                    let mut missing_styles = self.missing_styles.lock().unwrap();
+                    if !missing_styles.contains(name) {
+                        log::warn!("The theme doesn't have a style called \"{name}\"");
+                        missing_styles.insert(name.to_string());
                     }
+                    &EMPTY_STYLE
                 }
-            } else {
-                current_node.element.final_size_y += (current_node.element.layout.padding.top + current_node.element.layout.padding.bottom) as f32;
+            }
+        }
 
-                if current_node.element.layout.layout_direction == ChildLayoutDirection::TopToBottom {
-                    current_node.element.final_size_y += child_gap as f32;
-                }
+        /// Gives every style that doesn't have a font this one, since the presets can't come with one.
+        pub fn with_font(mut self, bitmap: &BitmapConfiguration, font_size: u32, line_height: f32) -> Self {
+            let font = Style::new().font(bitmap, font_size, line_height);
+            for style in self.styles.values_mut() {
+                *style = style.clone().inherit(&font);
+            }
+            self
+        }
 
-                match current_node.element.layout.sizing.height {
-                    SizingMode::Fixed(size) => {current_node.element.final_size_y = size as f32},
-                    SizingMode::Fit => {},
-                    SizingMode::Grow => {},
-                }
+        /// Dark blues, like the demo.
+        pub fn dark() -> Self {
+            Self::preset(ObjectColor(0, 0, 0, 128), [
+                ObjectColor(26, 67, 87, 255),
+                ObjectColor(17, 36, 46, 255),
+                ObjectColor(33, 61, 77, 255),
+                ObjectColor(51, 136, 175, 255),
+                ObjectColor(125, 209, 238, 255),
+                ObjectColor(255, 255, 255, 122)
+            ])
+        }
 
-                current_node.element.final_size_y = f32::max(current_node.element.final_size_y, current_node.element.layout.size_constraints.height.min as f32);
+        pub fn light() -> Self {
+            Self::preset(ObjectColor(235, 240, 244, 255), [
+                ObjectColor(205, 222, 232, 255),
+                ObjectColor(240, 245, 248, 255),
+                ObjectColor(170, 196, 212, 255),
+                ObjectColor(86, 150, 190, 255),
+                ObjectColor(30, 80, 110, 255),
+                ObjectColor(40, 60, 70, 90)
+            ])
+        }
 
-                if parent_node.element.layout.sizing.height == SizingMode::Fit || parent_node.element.layout.sizing.height == SizingMode::Grow {
-                    if parent_node.element.layout.layout_direction == ChildLayoutDirection::LeftToRight {
-                        parent_node.element.final_size_y = f32::max(current_node.element.final_size_y, parent_node.element.final_size_y)
-                    } else {
-                        parent_node.element.final_size_y += current_node.element.final_size_y;
-                    }
-                }
-            }
+        /// Both presets have the same styles, just in different colors.
+        fn preset(background: ObjectColor, [panel, surface, bar, button, highlight, note]: [ObjectColor; 6]) -> Self {
+            let panel_style = Style::new()
+                .rectangle(panel, CornerRadius::all(20.0))
+                .padding(Padding::all(10))
+                .child_gap(10);
+            let button_style = Style::new()
+                .rectangle(button, CornerRadius::all(10.0))
+                .alignment(ChildXAlignment::Center, ChildYAlignment::Center);
+
+            Theme::new(background)
+                .with_style("surface", Style::new().rectangle(surface, CornerRadius::all(15.0)).inherit(&panel_style))
+                .with_style("bar", Style::new().rectangle(bar, CornerRadius::all(15.0)).padding(Padding::all(5)).inherit(&panel_style))
+                .with_style("highlighted_button", Style::new().border(highlight, BorderWidth::all(2)).inherit(&button_style))
+                .with_style("note", Style::new().rectangle(note, CornerRadius::all(5.0)))
+                .with_style("panel", panel_style)
+                .with_style("button", button_style)
         }
+    }
 
-        pub(crate) fn size_along_axis(&mut self, left_to_right: bool, current_index: usize) {
-            let current_node = &mut self.layout_elements[current_index];
-            let mut growable_elements: Vec<usize> = vec![];
+    ///////////////////////////////////////////////////////
+    //////////////// Element State ////////////////////////
+    ///////////////////////////////////////////////////////
 
-            let sizing_along_axis =
-                (left_to_right && current_node.element.layout.layout_direction == ChildLayoutDirection::LeftToRight)
-                || (!left_to_right && current_node.element.layout.layout_direction == ChildLayoutDirection::TopToBottom);
+    /// A hashed element name, used as the key for element state.
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+    pub struct ElementId(u64);
 
-            let padding =
-                if left_to_right {current_node.element.layout.padding.left + current_node.element.layout.padding.right}
-                else { current_node.element.layout.padding.top + current_node.element.layout.padding.bottom };
-            let child_gap = (current_node.child_elements.len() as i32 - 1) * current_node.element.layout.child_gap;
-            let parent_size = if left_to_right {current_node.element.final_size_x} else {current_node.element.final_size_y};
+    impl ElementId {
+        pub const fn new(id: &str) -> Self {
+            ElementId(Self::hash(0xcbf29ce484222325, id.as_bytes()))
+        }
+
+        /// Tells apart elements that share a name, like the rows of a list.
+        pub const fn indexed(id: &str, index: u32) -> Self {
+            ElementId(Self::hash(Self::new(id).0, &index.to_le_bytes()))
+        }
+
+        // FNV-1a, so ids can be made in consts
+        const fn hash(mut hash: u64, bytes: &[u8]) -> u64 {
+            let mut i = 0;
+            while i < bytes.len() {
+                hash ^= bytes[i] as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+                i += 1;
+            }
+            hash
+        }
+    }
+
+    /// Keeps state for elements between frames, like whether a dropdown is open or how far something
+    /// is scrolled. Make one outside the frame loop and pass it to `begin_layout_with_state`.
+    #[derive(Default)]
+    pub struct StateStore {
+        entries: HashMap<ElementId, StateEntry>,
+        /// Counts up every frame, so entries know if they were seen in the current one
+        frame: u64
+    }
+
+    struct StateEntry {
+        value: Box<dyn Any>,
+        last_seen: u64
+    }
+
+    impl StateStore {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Reads an element's state outside of a frame.
+        pub fn get<T: 'static>(&self, id: ElementId) -> Option<&T> {
+            self.entries.get(&id)?.value.downcast_ref()
+        }
+
+        pub fn remove(&mut self, id: ElementId) {
+            self.entries.remove(&id);
+        }
+
+        pub fn len(&self) -> usize {
+            self.entries.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.entries.is_empty()
+        }
+
+        pub(crate) fn get_or_default<T: Default + 'static>(&mut self, id: ElementId) -> &mut T {
+            let frame = self.frame;
+            let entry = self.entries.entry(id).or_insert_with(|| StateEntry { value: Box::new(T::default()), last_seen: frame });
+            entry.last_seen = frame;
+
+            if !entry.value.is::<T>() {
+                log::warn!("The state for {id:?} was asked for as a different type, so it was reset");
+                entry.value = Box::new(T::default());
+            }
+            entry.value.downcast_mut().unwrap()
+        }
+
+        pub(crate) fn mark_seen(&mut self, id: ElementId) {
+            if let Some(entry) = self.entries.get_mut(&id) {
+                entry.last_seen = self.frame;
+            }
+        }
+
+        pub(crate) fn collect_garbage(&mut self) {
+            let frame = self.frame;
+            self.entries.retain(|_, entry| entry.last_seen == frame);
+        }
+    }
+
+    /// Where the context keeps element state. Without a store passed in it lasts as long as the context.
+    enum ElementState<'s> {
+        Stored(&'s mut StateStore),
+        Owned(StateStore)
+    }
+
+    impl ElementState<'_> {
+        fn store(&mut self) -> &mut StateStore {
+            match self {
+                ElementState::Stored(store) => store,
+                ElementState::Owned(store) => store
+            }
+        }
+    }
+
+    impl CatplushContext<'_> {
+        /// Gets the state kept for an element, starting it off as `T::default()` the first time. Asking
+        /// for it counts as seeing the element, so it isn't removed at the end of the frame.
+        pub fn state<T: Default + 'static>(&mut self, id: ElementId) -> &mut T {
+            self.state.store().get_or_default(id)
+        }
+    }
+
+    ///////////////////////////////////////////////////////
+    //////////////// Layout Cache /////////////////////////
+    ///////////////////////////////////////////////////////
+
+    #[derive(Default, Clone, Copy, PartialEq, Debug)]
+    enum CacheState {
+        /// Doesn't have an id or any children, or shares its id with another node, so it's never cached
+        #[default]
+        Uncached,
+        /// Nothing has changed since the last frame, so the cached layout is being used
+        Clean,
+        /// Laid out normally, and cached at the end of the frame
+        Dirty
+    }
+
+    #[derive(Default)]
+    struct CachedLayout {
+        subtree_hash: u64,
+        /// Where the nodes with a cached layout of their own are inside this one, since they aren't saved here
+        inner_roots_hash: u64,
+        initial_size: Vec2,
+        final_size: Vec2,
+        /// The nodes inside the cached one, in order, except for the ones inside nodes that are cached
+        /// themselves. Those are read from their own entry, so every node is only saved once.
+        nodes: Vec<CachedNode>,
+        /// False until a layout is saved at the end of the first frame the id is seen in
+        saved: bool,
+        /// The first node with this id this frame
+        owner: usize,
+        /// More than one node had this id this frame, so none of them are cached
+        duplicate: bool,
+        last_seen: u64
+    }
+
+    #[derive(Default)]
+    struct CachedNode {
+        size: Vec2,
+        /// From the top left of the cached node
+        position: Vec2,
+        split_indices: Vec<u32>
+    }
+
+    impl CatplushContext<'_> {
+        /// Elements with an id and children remember their layout between frames, and skip working it out
+        /// again when nothing inside of them changed and their parent gives them the same size. This only
+        /// does anything for contexts kept around with `new` and `reset`, and is on by default there.
+        pub fn set_layout_caching(&mut self, enabled: bool) {
+            self.layout_caching = enabled;
+            if !enabled {
+                self.layout_cache.clear();
+            }
+        }
+
+        /// Whether an element reused its layout from the last frame. Only meaningful after `solve`.
+        pub fn is_layout_cached(&self, id: ElementId) -> bool {
+            self.layout_elements.iter().any(|node| node.element.element_id == Some(id) && node.cache == CacheState::Clean)
+        }
+
+        /// Called when a node is closed, after all of its children have been.
+        fn hash_subtree(&mut self, index: usize) {
+            let mut hasher = DefaultHasher::new();
+            let element = &self.layout_elements[index].element;
+            element.element_id.hash(&mut hasher);
+            element.layout.hash(&mut hasher);
+
+            // Only what changes the size of the text, colors and textures don't matter
+            if let ObjectType::Text(text_data) = &element.object_type {
+                text_data.text.hash(&mut hasher);
+                text_data.split_indices.hash(&mut hasher);
+                text_data.font_size.hash(&mut hasher);
+                text_data.line_height.to_bits().hash(&mut hasher);
+                text_data.break_on_overflow.hash(&mut hasher);
+                let cell_size = text_data.font.cell_size();
+                (cell_size.x.to_bits(), cell_size.y.to_bits()).hash(&mut hasher);
+            }
+
+            for child in self.children(index) {
+                hasher.write_u64(self.layout_elements[child].subtree_hash);
+            }
+
+            let subtree_end = self.layout_elements.len();
+            let node = &mut self.layout_elements[index];
+            node.subtree_hash = hasher.finish();
+            node.subtree_end = subtree_end;
+        }
+
+        fn cached_layout(&self, index: usize) -> Option<&CachedLayout> {
+            let node = &self.layout_elements[index];
+            if node.cache != CacheState::Clean {
+                return None;
+            }
+            self.layout_cache.get(&node.element.element_id?)
+        }
+
+        /// Works out which nodes can use their cached layout this frame.
+        fn check_layout_cache(&mut self) {
+            if !self.layout_caching {
+                return;
+            }
+
+            for (index, node) in self.layout_elements.iter_mut().enumerate() {
+                node.cache = CacheState::Uncached;
+                let Some(element_id) = node.element.element_id else { continue };
+                if node.child_count == 0 || node.subtree_end <= index {
+                    continue;
+                }
+
+                node.cache = CacheState::Dirty;
+                let cached = self.layout_cache.entry(element_id).or_default();
+                if cached.last_seen != self.frame {
+                    cached.last_seen = self.frame;
+                    cached.owner = index;
+                    cached.duplicate = false;
+                } else if cached.owner != index {
+                    cached.duplicate = true;
+                    cached.saved = false;
+                }
+            }
+
+            // Backwards, so the nodes inside of each node are decided before it is
+            for index in (0..self.layout_elements.len()).rev() {
+                let node = &self.layout_elements[index];
+                if node.cache == CacheState::Uncached {
+                    continue;
+                }
+
+                let cached = &self.layout_cache[&node.element.element_id.unwrap()];
+                if cached.duplicate {
+                    self.layout_elements[index].cache = CacheState::Uncached;
+                    continue;
+                }
+
+                let (inner_roots_hash, inner_roots_clean) = inner_roots(&self.layout_elements, index);
+                if cached.saved && inner_roots_clean && cached.subtree_hash == node.subtree_hash && cached.inner_roots_hash == inner_roots_hash {
+                    self.layout_elements[index].cache = CacheState::Clean;
+                }
+            }
+        }
+
+        /// The node was given a different size than last frame, so its children have to be laid out after
+        /// all. They were skipped so far, so this catches them up to where the rest of the tree is.
+        fn invalidate_cached_layout(&mut self, index: usize, left_to_right: bool) {
+            self.layout_elements[index].cache = CacheState::Dirty;
+            let (size_x, size_y) = (self.layout_elements[index].element.final_size_x, self.layout_elements[index].element.final_size_y);
+
+            // The children add themselves onto the node's size, which is already decided
+            let mut children = self.child_cursor(index);
+            while let Some(child) = children.step(&self.layout_elements) {
+                self.initial_sizing_along_axis(true, child);
+            }
+            self.layout_elements[index].element.final_size_x = size_x;
+
+            if !left_to_right {
+                self.size_along_axis(true, index);
+                let mut children = self.child_cursor(index);
+                while let Some(child) = children.step(&self.layout_elements) {
+                    self.wrap_text(child);
+                }
+
+                let mut children = self.child_cursor(index);
+                while let Some(child) = children.step(&self.layout_elements) {
+                    self.initial_sizing_along_axis(false, child);
+                }
+                self.layout_elements[index].element.final_size_y = size_y;
+            }
+        }
+
+        /// Nodes inside that are cached themselves are read from their own entry, which the stack of
+        /// cursors keeps track of.
+        fn restore_cached_layout(&mut self, index: usize, left_to_right: bool) {
+            let mut cursors = std::mem::take(&mut self.cache_cursors);
+            cursors.clear();
+            cursors.push((index, 0));
+
+            for inner in index + 1..self.layout_elements[index].subtree_end {
+                while self.layout_elements[cursors.last().unwrap().0].subtree_end <= inner {
+                    cursors.pop();
+                }
+                let (root, position) = cursors.last_mut().unwrap();
+                let Some(cached_node) = self.layout_cache.get(&self.layout_elements[*root].element.element_id.unwrap()).and_then(|cached| cached.nodes.get(*position)) else {
+                    break;
+                };
+                *position += 1;
+
+                let [root_node, node] = self.layout_elements.get_disjoint_mut([*root, inner]).unwrap();
+                let (root_x, root_y) = (root_node.element.final_pos_x, root_node.element.final_pos_y);
+                let element = &mut node.element;
+                if left_to_right {
+                    element.final_size_x = cached_node.size.x;
+                    element.final_pos_x = root_x + cached_node.position.x;
+                } else {
+                    element.final_size_y = cached_node.size.y;
+                    element.final_pos_y = root_y + cached_node.position.y;
+                    if let ObjectType::Text(text_data) = &mut element.object_type {
+                        text_data.split_indices.clear();
+                        text_data.split_indices.extend_from_slice(&cached_node.split_indices);
+                    }
+                }
+
+                if node.cache != CacheState::Uncached {
+                    cursors.push((inner, 0));
+                }
+            }
+
+            self.cache_cursors = cursors;
+        }
+
+        /// Saves the layout of every node that was worked out this frame, and forgets about nodes that are gone.
+        fn update_layout_cache(&mut self) {
+            if !self.layout_caching {
+                return;
+            }
+
+            for (index, node) in self.layout_elements.iter().enumerate() {
+                if node.cache != CacheState::Dirty {
+                    continue;
+                }
+
+                let root = &node.element;
+                let Some(cached) = self.layout_cache.get_mut(&root.element_id.unwrap()) else { continue };
+                cached.subtree_hash = node.subtree_hash;
+                cached.inner_roots_hash = inner_roots(&self.layout_elements, index).0;
+                cached.initial_size = Vec2::new(node.initial_size_x, node.initial_size_y);
+                cached.final_size = Vec2::new(root.final_size_x, root.final_size_y);
+                cached.saved = true;
+
+                cached.nodes.resize_with(cached_inner_nodes(&self.layout_elements, index).count(), CachedNode::default);
+                for (cached_node, inner) in cached.nodes.iter_mut().zip(cached_inner_nodes(&self.layout_elements, index)) {
+                    let element = &self.layout_elements[inner].element;
+                    cached_node.size = Vec2::new(element.final_size_x, element.final_size_y);
+                    cached_node.position = Vec2::new(element.final_pos_x - root.final_pos_x, element.final_pos_y - root.final_pos_y);
+                    cached_node.split_indices.clear();
+                    if let ObjectType::Text(text_data) = &element.object_type {
+                        cached_node.split_indices.extend_from_slice(&text_data.split_indices);
+                    }
+                }
+            }
+
+            let frame = self.frame;
+            self.layout_cache.retain(|_, cached| cached.last_seen == frame);
+        }
+    }
+
+    /// The nodes inside a cached node that are saved in its entry: all of them except for the ones
+    /// inside other cached nodes.
+    fn cached_inner_nodes(nodes: &[Node], index: usize) -> impl Iterator<Item = usize> + '_ {
+        let end = nodes[index].subtree_end;
+        std::iter::successors(Some(index + 1).filter(|&first| first < end), move |&inner| {
+            let node = &nodes[inner];
+            let next = if node.cache == CacheState::Uncached { inner + 1 } else { node.subtree_end };
+            (next < end).then_some(next)
+        })
+    }
+
+    /// Hashes how many nodes are saved in a node's entry and where the cached nodes are among them, and
+    /// returns whether all of those are `Clean`, since the node can only be restored along with them.
+    fn inner_roots(nodes: &[Node], index: usize) -> (u64, bool) {
+        let mut hasher = DefaultHasher::new();
+        let mut all_clean = true;
+        let mut count = 0;
+
+        for inner in cached_inner_nodes(nodes, index) {
+            count += 1;
+            if nodes[inner].cache != CacheState::Uncached {
+                hasher.write_usize(inner - index);
+                all_clean &= nodes[inner].cache == CacheState::Clean;
+            }
+        }
+
+        hasher.write_usize(count);
+        (hasher.finish(), all_clean)
+    }
+
+    pub fn split_multiple_indices<'a>(text: &'a str, indices_to_split: &[u32]) -> Vec<&'a str> {
+        if indices_to_split.is_empty() {
+            return vec![text]
+        }
+
+        let mut segments: Vec<&str> = vec![];
+        let mut temporary_split: (&str, &str);
+        let mut current_split: &str = text;
+
+        for (index, split) in indices_to_split.iter().enumerate() {
+            if index == 0 {
+                temporary_split = current_split.split_at(*split as usize);
+            } else {
+                temporary_split = current_split.split_at((*split - indices_to_split[index - 1]) as usize);
+            }
+
+            segments.push(temporary_split.0);
+            if index == indices_to_split.len() - 1 {
+                segments.push(temporary_split.1)
+            }
+            current_split = temporary_split.1;
+        }
+
+        segments
+    }
+
+    pub fn find_largest_split(splits: &[u32], text_length: u32) -> u32 {
+        let starts = std::iter::once(0).chain(splits.iter().copied());
+        let ends = splits.iter().copied().chain(std::iter::once(text_length));
+        starts.zip(ends).map(|(start, end)| u32::abs_diff(start, end)).max().unwrap_or(0)
+    }
+
+    impl<'s> CatplushContext<'s> {
+        //////////// Layout Building Functions //////////////
+        #[track_caller]
+        pub fn open_element(&mut self, element: UiElement) {
+            if let Some(element_id) = element.element_id {
+                self.state.store().mark_seen(element_id);
+            }
+
+            let new_element_index = self.layout_elements.len();
+            let parent_index = self.open_layout_elements.last().copied();
+
+            if let Some(parent_index) = parent_index {
+                match self.layout_elements[parent_index].last_child {
+                    Some(last_child) => self.layout_elements[last_child].next_sibling = Some(new_element_index),
+                    None => self.layout_elements[parent_index].first_child = Some(new_element_index)
+                }
+                self.layout_elements[parent_index].last_child = Some(new_element_index);
+                self.layout_elements[parent_index].child_count += 1;
+            }
+
+            self.open_layout_elements.push(new_element_index);
+            self.layout_elements.push(Node {
+                parent: parent_index,
+                element,
+                location: Some(Location::caller()),
+                ..Default::default()
+            });
+        }
+
+        /// Closing more elements than were opened does nothing, but it shows up in `validate`.
+        #[track_caller]
+        pub fn close_element(&mut self) {
+            if self.open_layout_elements.len() <= 1 {
+                self.build_issues.push(LayoutIssue::ExtraClose { location: Location::caller() });
+                return
+            }
+            let index = self.open_layout_elements.pop().unwrap();
+            self.hash_subtree(index);
+        }
+
+        /// Opens an element, adds whatever `children` adds inside of it and closes it again, so it
+        /// can't be left open by accident. Returns what `children` returns.
+        #[track_caller]
+        pub fn element<R>(&mut self, element: UiElement, children: impl FnOnce(&mut Self) -> R) -> R {
+            self.open_element(element);
+            let result = children(self);
+            self.close_element();
+            result
+        }
+
+        /// Opens an element that gets closed when the returned guard is dropped. The guard can be
+        /// used like the context to add children.
+        #[track_caller]
+        pub fn open_scope(&mut self, element: UiElement) -> ElementGuard<'_, 's> {
+            self.open_element(element);
+            ElementGuard { context: self }
+        }
+
+        fn child_cursor(&self, index: usize) -> ChildCursor {
+            ChildCursor { next: self.layout_elements[index].first_child }
+        }
+
+        pub(crate) fn children(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+            std::iter::successors(self.layout_elements[index].first_child, |&child| self.layout_elements[child].next_sibling)
+        }
+
+        pub(crate) fn size_all(&mut self) {
+            self.initial_sizing_along_axis(true, 0);
+            self.size_along_axis(true, 0);
+            self.wrap_text(0);
+            self.initial_sizing_along_axis(false, 0);
+            self.size_along_axis(false, 0);
+        }
+
+        /// Goes through a subtree depth first without recursing, so deep trees can't overflow the stack. `enter`
+        /// is called on the way down and returns whether to go into the node's children, `leave` on the way back up.
+        fn traverse(&mut self, root_index: usize, mut enter: impl FnMut(&mut Self, usize) -> bool, mut leave: impl FnMut(&mut Self, usize)) {
+            // Passes can start inside other passes (like when a cached layout gets invalidated), so this
+            // only goes back down to where the stack was
+            let base = self.traversal_stack.len();
+            let children = if enter(self, root_index) { self.child_cursor(root_index) } else { ChildCursor { next: None } };
+            self.traversal_stack.push((root_index, children));
+
+            while self.traversal_stack.len() > base {
+                let (_, children) = self.traversal_stack.last_mut().unwrap();
+                match children.step(&self.layout_elements) {
+                    Some(child_index) => {
+                        let children = if enter(self, child_index) { self.child_cursor(child_index) } else { ChildCursor { next: None } };
+                        self.traversal_stack.push((child_index, children));
+                    },
+                    None => {
+                        let (current_index, _) = self.traversal_stack.pop().unwrap();
+                        leave(self, current_index);
+                    }
+                }
+            }
+        }
+
+        /// Children are sized before their parents, since they add onto them.
+        pub(crate) fn initial_sizing_along_axis(&mut self, left_to_right: bool, root_index: usize) {
+            self.traverse(
+                root_index,
+                // A cached node already knows how big it is, so its children don't need sizing
+                |ui, index| ui.cached_layout(index).is_none(),
+                |ui, index| ui.initial_sizing_of_element(left_to_right, index)
+            );
+        }
+
+        fn initial_sizing_of_element(&mut self, left_to_right: bool, current_index: usize) {
+            let cached_size = self.cached_layout(current_index).map(|cached| if left_to_right { cached.initial_size.x } else { cached.initial_size.y });
+
+            if self.layout_elements[current_index].parent.is_none() {
+                return;
+            }
+
+            let parent_index = self.layout_elements[current_index].parent.unwrap();
+            let [current_node, parent_node] = self.layout_elements.get_disjoint_mut([current_index, parent_index]).unwrap();
+
+            let child_gap = (current_node.child_count as i32 - 1) * current_node.element.layout.child_gap;
+
+            // Padding
+            if left_to_right {
+                if let Some(size) = cached_size {
+                    current_node.element.final_size_x = size;
+                } else {
+                    current_node.element.final_size_x += (current_node.element.layout.padding.left + current_node.element.layout.padding.right) as f32;
+
+                    if current_node.element.layout.layout_direction == ChildLayoutDirection::LeftToRight {
+                        current_node.element.final_size_x += child_gap as f32;
+                    }
+
+                    match current_node.element.layout.sizing.width {
+                        SizingMode::Fixed(size) => {current_node.element.final_size_x = size as f32},
+                        SizingMode::Fit => {},
+                        SizingMode::Grow => {},
+                    }
+
+                    current_node.element.final_size_x = f32::max(current_node.element.final_size_x, current_node.element.layout.size_constraints.width.min as f32);
+                }
+                current_node.initial_size_x = current_node.element.final_size_x;
+
+                if parent_node.element.layout.sizing.width == SizingMode::Fit || parent_node.element.layout.sizing.width == SizingMode::Grow {
+                    if parent_node.element.layout.layout_direction == ChildLayoutDirection::LeftToRight {
+                        parent_node.element.final_size_x += current_node.element.final_size_x;
+                    } else {
+                        parent_node.element.final_size_x = f32::max(current_node.element.final_size_x, parent_node.element.final_size_x)
+                    }
+                }
+            } else {
+                if let Some(size) = cached_size {
+                    current_node.element.final_size_y = size;
+                } else {
+                    current_node.element.final_size_y += (current_node.element.layout.padding.top + current_node.element.layout.padding.bottom) as f32;
+
+                    if current_node.element.layout.layout_direction == ChildLayoutDirection::TopToBottom {
+                        current_node.element.final_size_y += child_gap as f32;
+                    }
+
+                    match current_node.element.layout.sizing.height {
+                        SizingMode::Fixed(size) => {current_node.element.final_size_y = size as f32},
+                        SizingMode::Fit => {},
+                        SizingMode::Grow => {},
+                    }
+
+                    current_node.element.final_size_y = f32::max(current_node.element.final_size_y, current_node.element.layout.size_constraints.height.min as f32);
+                }
+                current_node.initial_size_y = current_node.element.final_size_y;
+
+                if parent_node.element.layout.sizing.height == SizingMode::Fit || parent_node.element.layout.sizing.height == SizingMode::Grow {
+                    if parent_node.element.layout.layout_direction == ChildLayoutDirection::LeftToRight {
+                        parent_node.element.final_size_y = f32::max(current_node.element.final_size_y, parent_node.element.final_size_y)
+                    } else {
+                        parent_node.element.final_size_y += current_node.element.final_size_y;
+                    }
+                }
+            }
+        }
+
+        /// Parents hand out their space before their children do.
+        pub(crate) fn size_along_axis(&mut self, left_to_right: bool, root_index: usize) {
+            self.traverse(root_index, |ui, index| ui.size_children_along_axis(left_to_right, index), |_, _| {});
+        }
+
+        /// Returns false if the children don't need sizing, because their layout is cached.
+        fn size_children_along_axis(&mut self, left_to_right: bool, current_index: usize) -> bool {
+            if let Some(cached) = self.cached_layout(current_index) {
+                let element = &self.layout_elements[current_index].element;
+                let unchanged = if left_to_right { element.final_size_x == cached.final_size.x } else { element.final_size_y == cached.final_size.y };
+                if unchanged {
+                    return false;
+                }
+                self.invalidate_cached_layout(current_index, left_to_right);
+            }
+
+            let current_node = &self.layout_elements[current_index];
+            // Borrowed from the context so it doesn't have to be allocated again for every element
+            let mut growable_elements = std::mem::take(&mut self.growable_elements);
+            growable_elements.clear();
+
+            let sizing_along_axis =
+                (left_to_right && current_node.element.layout.layout_direction == ChildLayoutDirection::LeftToRight)
+                || (!left_to_right && current_node.element.layout.layout_direction == ChildLayoutDirection::TopToBottom);
+
+            let padding =
+                if left_to_right {current_node.element.layout.padding.left + current_node.element.layout.padding.right}
+                else { current_node.element.layout.padding.top + current_node.element.layout.padding.bottom };
+            let child_gap = (current_node.child_count as i32 - 1) * current_node.element.layout.child_gap;
+            let parent_size = if left_to_right {current_node.element.final_size_x} else {current_node.element.final_size_y};
 
             let mut inner_content_size = 0.0;
-            for child in current_node.child_elements.clone() {
+            let mut children = self.child_cursor(current_index);
+            while let Some(child) = children.step(&self.layout_elements) {
                 if left_to_right {
                     inner_content_size += self.layout_elements[child].element.final_size_x;
                 } else {
@@ -682,18 +1751,21 @@ pub mod catplush_main {
                     *child_size = max_size
                 }
             }
+            self.growable_elements = growable_elements;
 
-            for child_index in self.layout_elements[current_index].child_elements.clone() {
-                self.size_along_axis(left_to_right, child_index);
-            }
+            true
         }
 
-        pub(crate) fn wrap_text(&mut self, current_index: usize) {
+        pub(crate) fn wrap_text(&mut self, root_index: usize) {
             // Not sure if this actually does need a depth first search
-            for child_index in self.layout_elements[current_index].child_elements.clone() {
-                self.wrap_text(child_index);
-            }
+            self.traverse(
+                root_index,
+                |ui, index| ui.cached_layout(index).is_none(),
+                |ui, index| if ui.cached_layout(index).is_none() { ui.wrap_text_of_element(index) }
+            );
+        }
 
+        fn wrap_text_of_element(&mut self, current_index: usize) {
             if self.layout_elements[current_index].parent.is_none() {
                 return;
             }
@@ -702,39 +1774,43 @@ pub mod catplush_main {
 
             let character_width: f32;
             let line_height: f32;
-            let text: String;
+            let text_length: usize;
             let break_on_overflow: bool;
 
-            match &mut self.layout_elements[current_index].element.object_type {
+            match &self.layout_elements[current_index].element.object_type {
                 ObjectType::Text(text_data) => {
-                    let font_size_factor = text_data.font_size as f32 / text_data.bitmap.cell_size.y;
-                    character_width = text_data.bitmap.cell_size.x * font_size_factor;
-                    line_height = text_data.bitmap.cell_size.y * font_size_factor * text_data.line_height;
-                    text = text_data.text.clone();
+                    let cell_size = text_data.font.cell_size();
+                    let font_size_factor = text_data.font_size as f32 / cell_size.y;
+                    character_width = cell_size.x * font_size_factor;
+                    line_height = cell_size.y * font_size_factor * text_data.line_height;
+                    text_length = text_data.text.len();
                     break_on_overflow = text_data.break_on_overflow
                 },
                 _ => { return }
             }
 
             let padding = self.layout_elements[parent_index].element.layout.padding.left + self.layout_elements[parent_index].element.layout.padding.right;
-            let child_gap = (self.layout_elements[parent_index].child_elements.len() as i32 - 1) * self.layout_elements[parent_index].element.layout.child_gap;
+            let child_gap = (self.layout_elements[parent_index].child_count as i32 - 1) * self.layout_elements[parent_index].element.layout.child_gap;
             let parent_size = self.layout_elements[parent_index].element.final_size_x;
 
             let mut inner_content_size = 0.0;
-            for child in self.layout_elements[current_index].child_elements.clone() {
+            for child in self.children(current_index) {
                 inner_content_size += self.layout_elements[child].element.final_size_x;
             }
 
             let available_width = parent_size - padding as f32 - child_gap as f32 - inner_content_size;
-            let number_of_wraps = ((character_width * text.len() as f32) / available_width) as i32;
-            let mut indexes_to_wrap: Vec<u32> = vec![];
+            let number_of_wraps = ((character_width * text_length as f32) / available_width) as i32;
             if number_of_wraps == 0 { return }
 
+            // The wraps go in between the new lines, so the split indices stay in order for `lines`
+            let element = &mut self.layout_elements[current_index].element;
+            let ObjectType::Text(text_data) = &mut element.object_type else { return };
+            let mut last_split = 0;
+
             for _ in 1..number_of_wraps {
                 let character_on_border: u32 = (available_width / character_width) as u32;
 
-                let last_split = *indexes_to_wrap.last().unwrap_or(&0) as usize;
-                let space_to_wrap: usize = match &text[last_split..character_on_border as usize].rfind(" ") {
+                let space_to_wrap: usize = match &text_data.text[last_split..character_on_border as usize].rfind(" ") {
                     Some(index) => { *index },
                     None => {
                         if break_on_overflow {
@@ -745,13 +1821,10 @@ pub mod catplush_main {
                     }
                 };
 
-                indexes_to_wrap.push(space_to_wrap as u32);
-                self.layout_elements[current_index].element.final_size_y += line_height;
-            }
-
-            // what is this syntax huh
-            if let ObjectType::Text(text_data) = &mut self.layout_elements[current_index].element.object_type {
-                text_data.split_indices.append(&mut indexes_to_wrap);
+                let position = text_data.split_indices.partition_point(|&split| split <= space_to_wrap as u32);
+                text_data.split_indices.insert(position, space_to_wrap as u32);
+                last_split = space_to_wrap;
+                element.final_size_y += line_height;
             }
         }
 
@@ -760,18 +1833,30 @@ pub mod catplush_main {
             self.position_along_axis(false, 0);
         }
 
-        pub(crate) fn position_along_axis(&mut self, left_to_right: bool, current_index: usize) {
-            let mut child_num = 1;
+        /// Parents are positioned before their children, which are placed relative to them.
+        pub(crate) fn position_along_axis(&mut self, left_to_right: bool, root_index: usize) {
+            self.traverse(root_index, |ui, index| ui.position_children_along_axis(left_to_right, index), |_, _| {});
+        }
+
+        /// Returns false if the children are already positioned, because their layout is cached.
+        fn position_children_along_axis(&mut self, left_to_right: bool, current_index: usize) -> bool {
+            if self.cached_layout(current_index).is_some() {
+                self.restore_cached_layout(current_index, left_to_right);
+                return false;
+            }
+
             let mut total_child_offset = 0.0;
 
-            for child in self.layout_elements[current_index].child_elements.clone() {
+            let mut children = self.child_cursor(current_index);
+            let mut child_num: usize = 0;
+            while let Some(child) = children.step(&self.layout_elements) {
                 if left_to_right {
                     match self.layout_elements[current_index].element.layout.layout_direction {
                         ChildLayoutDirection::LeftToRight => {
                             self.layout_elements[child].element.final_pos_x = total_child_offset
                                 + self.layout_elements[current_index].element.final_pos_x
                                 + self.layout_elements[current_index].element.layout.padding.left as f32
-                                + (self.layout_elements[current_index].element.layout.child_gap * (child_num - 1)) as f32;
+                                + (self.layout_elements[current_index].element.layout.child_gap * child_num as i32) as f32;
                         }
                         ChildLayoutDirection::TopToBottom => {
                             self.layout_elements[child].element.final_pos_x = self.layout_elements[current_index].element.final_pos_x
@@ -790,285 +1875,1510 @@ pub mod catplush_main {
                             self.layout_elements[child].element.final_pos_y = total_child_offset
                                 + self.layout_elements[current_index].element.final_pos_y
                                 + self.layout_elements[current_index].element.layout.padding.top as f32
-                                + (self.layout_elements[current_index].element.layout.child_gap * (child_num - 1)) as f32;
+                                + (self.layout_elements[current_index].element.layout.child_gap * child_num as i32) as f32;
                         }
                     };
 
                     total_child_offset += self.layout_elements[child].element.final_size_y;
                 }
-                child_num += 1;
+                child_num += 1;
+            }
+
+            let aligning_along_axis =
+                if left_to_right { self.layout_elements[current_index].element.layout.layout_direction == ChildLayoutDirection::LeftToRight
+                   && self.layout_elements[current_index].element.layout.child_alignment.x != ChildXAlignment::Left }
+                else { self.layout_elements[current_index].element.layout.layout_direction == ChildLayoutDirection::TopToBottom
+                    && self.layout_elements[current_index].element.layout.child_alignment.y != ChildYAlignment::Top };
+
+            let padding =
+                if left_to_right {self.layout_elements[current_index].element.layout.padding.left + self.layout_elements[current_index].element.layout.padding.right}
+                else { self.layout_elements[current_index].element.layout.padding.top + self.layout_elements[current_index].element.layout.padding.bottom };
+            let child_gap = (self.layout_elements[current_index].child_count as i32 - 1) * self.layout_elements[current_index].element.layout.child_gap;
+            let parent_size = if left_to_right {self.layout_elements[current_index].element.final_size_x} else {self.layout_elements[current_index].element.final_size_y};
+
+            let mut inner_content_size = 0.0;
+            for child in self.children(current_index) {
+                if left_to_right {
+                    inner_content_size += self.layout_elements[child].element.final_size_x;
+                } else {
+                    inner_content_size += self.layout_elements[child].element.final_size_y;
+                }
+            }
+
+            let mut distance_to_add = parent_size - padding as f32 - child_gap as f32 - inner_content_size;
+
+            let mut children = self.child_cursor(current_index);
+            while let Some(child_index) = children.step(&self.layout_elements) {
+                if !aligning_along_axis {
+                    distance_to_add =
+                        if left_to_right {
+                            parent_size - padding as f32 - self.layout_elements[child_index].element.final_size_x
+                        } else {
+                            parent_size - padding as f32 - self.layout_elements[child_index].element.final_size_y
+                        };
+                }
+                if left_to_right {
+                    match self.layout_elements[current_index].element.layout.child_alignment.x {
+                        ChildXAlignment::Left => {},
+                        ChildXAlignment::Center => {
+                            self.layout_elements[child_index].element.final_pos_x += distance_to_add/2.0
+                        },
+                        ChildXAlignment::Right => {
+                            self.layout_elements[child_index].element.final_pos_x += distance_to_add
+                        },
+                    }
+                } else {
+                    match self.layout_elements[current_index].element.layout.child_alignment.y {
+                        ChildYAlignment::Top => {},
+                        ChildYAlignment::Center => {
+                            self.layout_elements[child_index].element.final_pos_y += distance_to_add/2.0;
+                        },
+                        ChildYAlignment::Bottom => {
+                            self.layout_elements[child_index].element.final_pos_y += distance_to_add;
+                        },
+                    }
+                }
+            }
+
+            true
+        }
+
+        // Solves all sizing and positioning and returns a set of render commands for passing to the
+        // renderer. They borrow text from the context, so they have to be drawn before the next `reset`.
+        pub fn end_layout(&mut self) -> Vec<RenderCommand<'_>> {
+            self.solve();
+            self.render_commands().collect()
+        }
+
+        /// Same as `end_layout`, but also returns a dump of the whole solved tree for debugging.
+        pub fn end_layout_with_dump(&mut self) -> (Vec<RenderCommand<'_>>, LayoutDump) {
+            self.solve();
+            (self.render_commands().collect(), self.dump())
+        }
+
+        /// Works out the size and position of every element. `end_layout` does this for you.
+        pub fn solve(&mut self) {
+            for issue in self.validate() {
+                log::warn!("{issue}");
+            }
+
+            self.state.store().collect_garbage();
+
+            self.open_layout_elements.clear();
+
+            self.check_layout_cache();
+            self.size_all();
+            self.position_all();
+            self.update_layout_cache();
+        }
+
+        /// Returns the render commands for a solved layout without collecting them into a list, which
+        /// together with `solve` and `reset` gets through a frame without allocating.
+        pub fn render_commands(&self) -> impl Iterator<Item = RenderCommand<'_>> + '_ {
+            let mut debug_commands = vec![];
+            if let Some(debug_view) = &self.debug_view {
+                self.debug_overlay(debug_view, &mut debug_commands);
+            }
+
+            let (fonts, context) = (&self.fonts, self.id);
+            self.layout_elements.iter().skip(1).map(move |node| render_command(fonts, context, node)).chain(debug_commands)
+        }
+    }
+
+    fn render_command<'a>(fonts: &'a [Font], context: u32, node: &'a Node) -> RenderCommand<'a> {
+        let element = &node.element;
+        let bounding_box = BoundingBox { x: element.final_pos_x, y: element.final_pos_y, width: element.final_size_x, height: element.final_size_y };
+        let render_data = match &element.object_type {
+            ObjectType::Unset => RenderData::NoType,
+            ObjectType::Rectangle => {
+                RenderData::RectangleData(RectangleRenderData { color: element.color, stroke_color: element.stroke_color, corner_radius: element.corner_radius, border_width: element.border_width })
+            },
+            ObjectType::Text(data) => {
+                let font = match &data.font {
+                    TextFont::Bitmap(bitmap) => Some((&**bitmap, None)),
+                    TextFont::Handle(handle) => find_font(fonts, context, *handle).map(|font| (&font.bitmap, Some(font)))
+                };
+                match font {
+                    Some((bitmap, font)) => RenderData::TextData(TextRenderData {
+                        bitmap,
+                        font,
+                        text: Cow::Borrowed(&data.text),
+                        font_size: data.font_size,
+                        line_height: data.line_height,
+                        split_indices: Cow::Borrowed(&data.split_indices)
+                    }),
+                    // The handle is from a different context, which `validate` reports
+                    None => RenderData::NoType
+                }
+            },
+            ObjectType::Image(data) => {
+                let texture_size = Vec2::new(data.texture.width as f32, data.texture.height as f32);
+                let (draw_box, uv_pos, uv_size) = data.fit.apply(texture_size, bounding_box);
+
+                // The fit works on the image's own uvs, so they need to be moved into the texture's sub-rectangle
+                RenderData::ImageData(TextureRenderData {
+                    texture_id: data.texture.texture_id,
+                    draw_box,
+                    uv_pos: data.texture.uv_pos + uv_pos * data.texture.uv_size,
+                    uv_size: uv_size * data.texture.uv_size
+                })
+            },
+            ObjectType::NineSlice(data) => {
+                RenderData::NineSliceData(NineSliceRenderData::new(data, bounding_box))
+            }
+        };
+
+        let id = element.id.unwrap_or_default();
+
+        RenderCommand { bounding_box, render_data, id }
+    }
+
+    ////////////////////////////////////////////////
+    //////////////// Debug View ////////////////////
+    ////////////////////////////////////////////////
+
+    /// The id given to every render command drawn by the debug view.
+    pub const DEBUG_VIEW_ID: &str = "catplush_debug_view";
+
+    const DEBUG_OUTLINE_COLOR: ObjectColor = ObjectColor(255, 0, 255, 160);
+    const DEBUG_HOVER_COLOR: ObjectColor = ObjectColor(255, 230, 0, 255);
+    const DEBUG_PADDING_COLOR: ObjectColor = ObjectColor(80, 200, 120, 90);
+    const DEBUG_GAP_COLOR: ObjectColor = ObjectColor(240, 150, 40, 90);
+    const DEBUG_PANEL_COLOR: ObjectColor = ObjectColor(20, 20, 28, 235);
+
+    struct DebugView {
+        bitmap: BitmapConfiguration,
+        pointer: Option<Vec2>
+    }
+
+    impl CatplushContext<'_> {
+        /// Turns on the layout inspector, which draws over the finished layout. Every element gets an
+        /// outline, padding and child gaps are shaded in, and the element under the pointer gets a panel
+        /// showing how it was sized. The bitmap is used for the panel's text.
+        pub fn debug_view(&mut self, bitmap: &BitmapConfiguration, pointer: Option<(f32, f32)>) {
+            self.debug_view = Some(DebugView {
+                bitmap: bitmap.clone(),
+                pointer: pointer.map(|(x, y)| Vec2::new(x, y))
+            });
+        }
+
+        fn debug_overlay<'a>(&self, debug_view: &'a DebugView, render_commands: &mut Vec<RenderCommand<'a>>) {
+            let mut push_rectangle = |bounding_box: BoundingBox, color: ObjectColor, stroke_color: ObjectColor, border_width: i32| {
+                render_commands.push(RenderCommand {
+                    bounding_box,
+                    render_data: RenderData::RectangleData(RectangleRenderData {
+                        color,
+                        stroke_color,
+                        corner_radius: CornerRadius::default(),
+                        border_width: BorderWidth::all(border_width)
+                    }),
+                    id: DEBUG_VIEW_ID
+                });
+            };
+
+            let mut hovered = None;
+            for (index, node) in self.layout_elements.iter().enumerate().skip(1) {
+                let element = &node.element;
+                let layout = &element.layout;
+                let bounding_box = BoundingBox { x: element.final_pos_x, y: element.final_pos_y, width: element.final_size_x, height: element.final_size_y };
+
+                let padding = layout.padding;
+                let inner_height = bounding_box.height - (padding.top + padding.bottom) as f32;
+                let padding_boxes = [
+                    BoundingBox { x: bounding_box.x, y: bounding_box.y, width: bounding_box.width, height: padding.top as f32 },
+                    BoundingBox { x: bounding_box.x, y: bounding_box.y + bounding_box.height - padding.bottom as f32, width: bounding_box.width, height: padding.bottom as f32 },
+                    BoundingBox { x: bounding_box.x, y: bounding_box.y + padding.top as f32, width: padding.left as f32, height: inner_height },
+                    BoundingBox { x: bounding_box.x + bounding_box.width - padding.right as f32, y: bounding_box.y + padding.top as f32, width: padding.right as f32, height: inner_height }
+                ];
+                for padding_box in padding_boxes {
+                    if padding_box.width > 0.0 && padding_box.height > 0.0 {
+                        push_rectangle(padding_box, DEBUG_PADDING_COLOR, ObjectColor::TRANSPARENT, 0);
+                    }
+                }
+
+                if layout.child_gap > 0 {
+                    let gap = layout.child_gap as f32;
+                    // Every child but the last has a gap after it
+                    for child_index in self.children(index).filter(|&child| self.layout_elements[child].next_sibling.is_some()) {
+                        let child = &self.layout_elements[child_index].element;
+                        let gap_box = match layout.layout_direction {
+                            ChildLayoutDirection::LeftToRight => BoundingBox { x: child.final_pos_x + child.final_size_x, y: bounding_box.y + padding.top as f32, width: gap, height: inner_height },
+                            ChildLayoutDirection::TopToBottom => BoundingBox { x: bounding_box.x + padding.left as f32, y: child.final_pos_y + child.final_size_y, width: bounding_box.width - (padding.left + padding.right) as f32, height: gap }
+                        };
+                        push_rectangle(gap_box, DEBUG_GAP_COLOR, ObjectColor::TRANSPARENT, 0);
+                    }
+                }
+
+                push_rectangle(bounding_box, ObjectColor::TRANSPARENT, DEBUG_OUTLINE_COLOR, 1);
+
+                // Children always come after their parents, so the last match is the deepest element
+                if let Some(pointer) = debug_view.pointer {
+                    if pointer.x >= bounding_box.x && pointer.y >= bounding_box.y && pointer.x < bounding_box.x + bounding_box.width && pointer.y < bounding_box.y + bounding_box.height {
+                        hovered = Some((index, bounding_box));
+                    }
+                }
+            }
+
+            let (Some(pointer), Some((hovered_index, hovered_box))) = (debug_view.pointer, hovered) else {
+                return;
+            };
+            push_rectangle(hovered_box, ObjectColor::TRANSPARENT, DEBUG_HOVER_COLOR, 2);
+
+            let text = self.debug_description(hovered_index, &debug_view.bitmap);
+            let font_size = 16;
+            let line_height = 1.2;
+            let glyph_size = debug_view.bitmap.cell_size * (font_size as f32 / debug_view.bitmap.cell_size.y);
+            let split_indices: Vec<u32> = text.match_indices('\n').enumerate().map(|(index, (position, _))| (position - index) as u32).collect();
+            let text = text.replace('\n', "");
+
+            let panel_padding = 8.0;
+            let text_size = Vec2::new(
+                find_largest_split(&split_indices, text.len() as u32) as f32 * glyph_size.x,
+                (split_indices.len() + 1) as f32 * glyph_size.y * line_height
+            );
+            let panel_size = text_size + Vec2::splat(panel_padding * 2.0);
+
+            // Next to the pointer, flipped to the other side if it would go off the window
+            let window_size = Vec2::new(self.layout_elements[0].element.final_size_x, self.layout_elements[0].element.final_size_y);
+            let mut panel_pos = pointer + Vec2::splat(16.0);
+            if panel_pos.x + panel_size.x > window_size.x { panel_pos.x = pointer.x - 16.0 - panel_size.x; }
+            if panel_pos.y + panel_size.y > window_size.y { panel_pos.y = pointer.y - 16.0 - panel_size.y; }
+            let panel_pos = panel_pos.max(Vec2::ZERO);
+
+            push_rectangle(BoundingBox { x: panel_pos.x, y: panel_pos.y, width: panel_size.x, height: panel_size.y }, DEBUG_PANEL_COLOR, DEBUG_HOVER_COLOR, 1);
+            render_commands.push(RenderCommand {
+                bounding_box: BoundingBox { x: panel_pos.x + panel_padding, y: panel_pos.y + panel_padding, width: text_size.x, height: text_size.y },
+                render_data: RenderData::TextData(TextRenderData {
+                    bitmap: &debug_view.bitmap,
+                    font: None,
+                    text: Cow::Owned(text),
+                    font_size,
+                    line_height,
+                    split_indices: Cow::Owned(split_indices)
+                }),
+                id: DEBUG_VIEW_ID
+            });
+        }
+
+        /// The text in the hover panel, one line per property. Characters missing from the bitmap are left out.
+        fn debug_description(&self, index: usize, bitmap: &BitmapConfiguration) -> String {
+            let element = &self.layout_elements[index].element;
+            let layout = &element.layout;
+
+            let kind = match element.object_type {
+                ObjectType::Unset => "Element",
+                ObjectType::Rectangle => "Rectangle",
+                ObjectType::Text(_) => "Text",
+                ObjectType::Image(_) => "Image",
+                ObjectType::NineSlice(_) => "Nine slice"
+            };
+            let limit = |limit: SizeLimit| {
+                if limit.max == i32::MAX { format!("min {}", limit.min) } else { format!("min {} max {}", limit.min, limit.max) }
+            };
+            let padding = layout.padding;
+
+            let description = [
+                format!("#{index} {kind} {}", element.id.unwrap_or_default()).trim_end().to_string(),
+                format!("width: {:?} ({})", layout.sizing.width, limit(layout.size_constraints.width)),
+                format!("height: {:?} ({})", layout.sizing.height, limit(layout.size_constraints.height)),
+                format!("size: {} x {}", element.final_size_x, element.final_size_y),
+                format!("position: {}, {}", element.final_pos_x, element.final_pos_y),
+                format!("padding: {} {} {} {}", padding.left, padding.right, padding.top, padding.bottom),
+                format!("gap: {} {:?}", layout.child_gap, layout.layout_direction)
+            ].join("\n");
+
+            description.chars().filter(|&char| char == '\n' || bitmap.character_list.contains(char)).collect()
+        }
+    }
+
+    /////////////////////////////////////////////////////
+    ////////////////  Render Structures  ////////////////
+    /////////////////////////////////////////////////////
+
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    pub struct BoundingBox {
+        pub x: f32,
+        pub y: f32,
+        pub width: f32,
+        pub height: f32
+    }
+
+    pub struct RectangleRenderData {
+        pub color: ObjectColor,
+        pub stroke_color: ObjectColor,
+        pub corner_radius: CornerRadius,
+        pub border_width: BorderWidth
+    }
+
+
+    /// Borrows from the element it was made from, apart from text made up while rendering like the debug view's.
+    pub struct TextRenderData<'a> {
+        pub bitmap: &'a BitmapConfiguration,
+        /// Set when the element was made with `font_text`, for looking glyphs up without searching the bitmap
+        pub font: Option<&'a Font>,
+        pub text: Cow<'a, str>,
+        pub font_size: u32,
+        pub line_height: f32,
+        /// Where the text is split into lines, from first to last
+        pub split_indices: Cow<'a, [u32]>
+    }
+
+    impl TextRenderData<'_> {
+        /// Splits the text into the lines it was wrapped into.
+        pub fn lines(&self) -> impl Iterator<Item = &str> + '_ {
+            let starts = std::iter::once(0).chain(self.split_indices.iter().map(|&split| split as usize));
+            let ends = self.split_indices.iter().map(|&split| split as usize).chain(std::iter::once(self.text.len()));
+            starts.zip(ends).map(|(start, end)| &self.text[start..end])
+        }
+
+        /// How much the bitmap's cells are scaled by to reach the font size.
+        pub fn scale_factor(&self) -> f32 {
+            self.font_size as f32 / self.bitmap.cell_size.y
+        }
+
+        /// Returns a textured quad for every character, for backends that draw text straight from the bitmap.
+        pub fn glyphs(&self, position: Vec2) -> impl Iterator<Item = TextureRenderData> + '_ {
+            let scale_factor = self.scale_factor();
+            let glyph_size = self.bitmap.cell_size * scale_factor;
+            let line_advance = glyph_size.y * self.line_height;
+
+            self.lines().enumerate().flat_map(move |(line_number, line)| {
+                let line_position = position.y + line_advance * line_number as f32 + 1.0;
+
+                line.chars().enumerate().filter_map(move |(i, char)| {
+                    let (uv_pos, uv_size) = match self.font {
+                        Some(font) => font.glyph_uv(char)?,
+                        None => self.bitmap.glyph_uv(char)?
+                    };
+
+                    Some(TextureRenderData {
+                        texture_id: self.bitmap.texture,
+                        draw_box: BoundingBox { x: position.x + i as f32 * glyph_size.x, y: line_position, width: glyph_size.x, height: glyph_size.y },
+                        uv_pos,
+                        uv_size
+                    })
+                })
+            })
+        }
+    }
+
+    pub struct TextureRenderData {
+        pub texture_id: NonZeroU32,
+        /// Where the image is actually drawn, which can be smaller than the element depending on its `ImageFit`.
+        pub draw_box: BoundingBox,
+        pub uv_pos: Vec2,
+        pub uv_size: Vec2,
+
+        // Need to implement these myself.
+        // pub tint: ObjectColor,
+        // pub corner_radius: CornerRadius
+    }
+
+    pub struct NineSliceRenderData {
+        pub texture_id: NonZeroU32,
+        /// Where each column and row of slices starts and ends on the screen.
+        pub x_edges: [f32; 4],
+        pub y_edges: [f32; 4],
+        /// Where each column and row of slices starts and ends in the texture.
+        pub u_edges: [f32; 4],
+        pub v_edges: [f32; 4]
+    }
+
+    impl NineSliceRenderData {
+        pub(crate) fn new(data: &CatplushNineSliceData, bounding_box: BoundingBox) -> Self {
+            let texture = &data.texture;
+            let insets = &data.insets;
+
+            // If the element is smaller than the corners, shrink the corners so they still fit
+            let horizontal_scale = f32::min(1.0, bounding_box.width / (insets.left + insets.right).max(1) as f32);
+            let vertical_scale = f32::min(1.0, bounding_box.height / (insets.top + insets.bottom).max(1) as f32);
+
+            let left = insets.left as f32 * horizontal_scale;
+            let right = insets.right as f32 * horizontal_scale;
+            let top = insets.top as f32 * vertical_scale;
+            let bottom = insets.bottom as f32 * vertical_scale;
+
+            let texture_size = Vec2::new(texture.width as f32, texture.height as f32);
+            let u_edges = [0.0, insets.left as f32 / texture_size.x, 1.0 - insets.right as f32 / texture_size.x, 1.0];
+            let v_edges = [0.0, insets.top as f32 / texture_size.y, 1.0 - insets.bottom as f32 / texture_size.y, 1.0];
+
+            NineSliceRenderData {
+                texture_id: texture.texture_id,
+                x_edges: [bounding_box.x, bounding_box.x + left, bounding_box.x + bounding_box.width - right, bounding_box.x + bounding_box.width],
+                y_edges: [bounding_box.y, bounding_box.y + top, bounding_box.y + bounding_box.height - bottom, bounding_box.y + bounding_box.height],
+                // Moved into the texture's sub-rectangle
+                u_edges: u_edges.map(|u| texture.uv_pos.x + u * texture.uv_size.x),
+                v_edges: v_edges.map(|v| texture.uv_pos.y + v * texture.uv_size.y)
+            }
+        }
+
+        /// Returns the nine slices as separate images, left to right, then top to bottom.
+        pub fn slices(&self) -> impl Iterator<Item = TextureRenderData> + '_ {
+            (0..9).map(|index| {
+                let (column, row) = (index % 3, index / 3);
+                let uv_pos = Vec2::new(self.u_edges[column], self.v_edges[row]);
+
+                TextureRenderData {
+                    texture_id: self.texture_id,
+                    draw_box: BoundingBox {
+                        x: self.x_edges[column],
+                        y: self.y_edges[row],
+                        width: self.x_edges[column + 1] - self.x_edges[column],
+                        height: self.y_edges[row + 1] - self.y_edges[row]
+                    },
+                    uv_pos,
+                    uv_size: Vec2::new(self.u_edges[column + 1], self.v_edges[row + 1]) - uv_pos
+                }
+            })
+        }
+    }
+
+    /// More kinds of commands can be added later, so matches on it need a `_` arm.
+    #[non_exhaustive]
+    pub enum RenderData<'a> {
+        NoType,
+        RectangleData(RectangleRenderData),
+        TextData(TextRenderData<'a>),
+        ImageData(TextureRenderData),
+        NineSliceData(NineSliceRenderData)
+    }
+
+    pub struct RenderCommand<'a> {
+        pub bounding_box: BoundingBox,
+
+        pub render_data: RenderData<'a>,
+
+        pub id: &'static str,
+    }
+
+    /// Something that draws render commands, like a GPU renderer or an image in memory. Implement the
+    /// `draw_*` functions and call `render` with the commands returned by `end_layout`.
+    pub trait RenderBackend {
+        fn draw_rectangle(&mut self, bounding_box: BoundingBox, data: &RectangleRenderData);
+
+        fn draw_text(&mut self, bounding_box: BoundingBox, data: &TextRenderData);
+
+        /// Images are drawn in `data.draw_box`, which isn't always the same as the element's bounding box.
+        fn draw_image(&mut self, bounding_box: BoundingBox, data: &TextureRenderData);
+
+        /// Draws each slice as its own image by default.
+        fn draw_nine_slice(&mut self, bounding_box: BoundingBox, data: &NineSliceRenderData) {
+            for slice in data.slices() {
+                self.draw_image(bounding_box, &slice);
+            }
+        }
+
+        /// Called once every command has been drawn.
+        fn finish(&mut self) {}
+
+        /// Takes a list of commands, like `&end_layout()`, or the iterator from `render_commands`.
+        fn render<'a>(&mut self, render_commands: impl IntoIterator<Item = impl Borrow<RenderCommand<'a>>>) where Self: Sized {
+            for render_command in render_commands {
+                self.draw_command(render_command.borrow());
+            }
+
+            self.finish();
+        }
+
+        /// Like `render`, but only takes a slice so it can be called on a `dyn RenderBackend`.
+        fn render_slice(&mut self, render_commands: &[RenderCommand]) {
+            for render_command in render_commands {
+                self.draw_command(render_command);
+            }
+
+            self.finish();
+        }
+
+        /// Draws a single command without calling `finish`.
+        fn draw_command(&mut self, render_command: &RenderCommand) {
+            let bounding_box = render_command.bounding_box;
+
+            match &render_command.render_data {
+                RenderData::NoType => {},
+                RenderData::RectangleData(data) => self.draw_rectangle(bounding_box, data),
+                RenderData::TextData(data) => self.draw_text(bounding_box, data),
+                RenderData::ImageData(data) => self.draw_image(bounding_box, data),
+                RenderData::NineSliceData(data) => self.draw_nine_slice(bounding_box, data)
+            }
+        }
+    }
+
+    ///////////////////////////////////////////////////////
+    //////////////// Damage Tracking //////////////////////
+    ///////////////////////////////////////////////////////
+
+    /// A render command boiled down to where it draws and a hash of what it draws.
+    #[derive(Clone, Copy, PartialEq)]
+    struct DrawnCommand {
+        area: BoundingBox,
+        hash: u64
+    }
+
+    /// Compares each frame's render commands with the last frame's to find what changed on screen. Keep one
+    /// outside the frame loop and call `update` with the commands before drawing them.
+    pub struct DamageTracker {
+        previous: Vec<DrawnCommand>,
+        current: Vec<DrawnCommand>,
+        // Sorted copies, kept around so comparing doesn't allocate
+        sorted_previous: Vec<DrawnCommand>,
+        sorted_current: Vec<DrawnCommand>,
+        damage: Vec<BoundingBox>,
+        invalidated: bool,
+        /// Set by `update` after `invalidate`, since then the whole window needs drawing, not just the commands
+        fully_damaged: bool
+    }
+
+    impl Default for DamageTracker {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl DamageTracker {
+        pub fn new() -> Self {
+            Self {
+                previous: vec![],
+                current: vec![],
+                sorted_previous: vec![],
+                sorted_current: vec![],
+                damage: vec![],
+                invalidated: true,
+                fully_damaged: false
+            }
+        }
+
+        /// Compares the commands with the ones from the last call and returns the areas that need to be
+        /// drawn again. The first frame, and the one after `invalidate`, is damaged everywhere anything is
+        /// drawn, and `is_fully_damaged` is set since the rest of the window needs clearing too.
+        pub fn update<'a>(&mut self, render_commands: impl IntoIterator<Item = impl Borrow<RenderCommand<'a>>>) -> &[BoundingBox] {
+            std::mem::swap(&mut self.previous, &mut self.current);
+            self.current.clear();
+            self.current.extend(render_commands.into_iter().map(|command| {
+                let command = command.borrow();
+                DrawnCommand { area: drawn_area(command), hash: hash_render_command(command) }
+            }));
+
+            self.damage.clear();
+
+            self.fully_damaged = std::mem::take(&mut self.invalidated);
+            if self.fully_damaged {
+                self.damage.extend(self.current.iter().map(|command| command.area));
+            } else {
+                self.compare_frames();
+            }
+
+            self.damage.retain(|area| area.width > 0.0 && area.height > 0.0);
+            self.damage.sort_unstable_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)).then(a.width.total_cmp(&b.width)).then(a.height.total_cmp(&b.height)));
+            self.damage.dedup();
+
+            &self.damage
+        }
+
+        fn compare_frames(&mut self) {
+            let by_hash = |command: &DrawnCommand| command.hash;
+
+            self.sorted_previous.clear();
+            self.sorted_previous.extend_from_slice(&self.previous);
+            self.sorted_previous.sort_unstable_by_key(by_hash);
+            self.sorted_current.clear();
+            self.sorted_current.extend_from_slice(&self.current);
+            self.sorted_current.sort_unstable_by_key(by_hash);
+
+            // Whatever only shows up in one of the two frames was either removed or added
+            let (mut previous, mut current) = (0, 0);
+            while previous < self.sorted_previous.len() || current < self.sorted_current.len() {
+                let ordering = match (self.sorted_previous.get(previous), self.sorted_current.get(current)) {
+                    (Some(a), Some(b)) => a.hash.cmp(&b.hash),
+                    (Some(_), None) => Ordering::Less,
+                    _ => Ordering::Greater
+                };
+
+                match ordering {
+                    Ordering::Equal => { previous += 1; current += 1; },
+                    Ordering::Less => { self.damage.push(self.sorted_previous[previous].area); previous += 1; },
+                    Ordering::Greater => { self.damage.push(self.sorted_current[current].area); current += 1; }
+                }
+            }
+
+            // Commands drawn in a different order, which matters where they overlap
+            if self.previous != self.current {
+                for (a, b) in self.previous.iter().zip(&self.current) {
+                    if a != b {
+                        self.damage.push(a.area);
+                        self.damage.push(b.area);
+                    }
+                }
             }
+        }
 
-            let aligning_along_axis =
-                if left_to_right { self.layout_elements[current_index].element.layout.layout_direction == ChildLayoutDirection::LeftToRight
-                   && self.layout_elements[current_index].element.layout.child_alignment.x != ChildXAlignment::Left }
-                else { self.layout_elements[current_index].element.layout.layout_direction == ChildLayoutDirection::TopToBottom
-                    && self.layout_elements[current_index].element.layout.child_alignment.y != ChildYAlignment::Top };
+        /// The areas found by the last `update`. They can overlap.
+        pub fn damage(&self) -> &[BoundingBox] {
+            &self.damage
+        }
 
-            let padding =
-                if left_to_right {self.layout_elements[current_index].element.layout.padding.left + self.layout_elements[current_index].element.layout.padding.right}
-                else { self.layout_elements[current_index].element.layout.padding.top + self.layout_elements[current_index].element.layout.padding.bottom };
-            let child_gap = (self.layout_elements[current_index].child_elements.len() as i32 - 1) * self.layout_elements[current_index].element.layout.child_gap;
-            let parent_size = if left_to_right {self.layout_elements[current_index].element.final_size_x} else {self.layout_elements[current_index].element.final_size_y};
+        /// Whether the last `update` found nothing that needs drawing again, so the last frame can stay on screen.
+        pub fn is_unchanged(&self) -> bool {
+            !self.fully_damaged && self.damage.is_empty()
+        }
 
-            let mut inner_content_size = 0.0;
-            for child in self.layout_elements[current_index].child_elements.clone() {
-                if left_to_right {
-                    inner_content_size += self.layout_elements[child].element.final_size_x;
-                } else {
-                    inner_content_size += self.layout_elements[child].element.final_size_y;
+        /// Whether the last `update` was the first one or came after `invalidate`, so the whole window has
+        /// to be drawn again, even where nothing is drawn.
+        pub fn is_fully_damaged(&self) -> bool {
+            self.fully_damaged
+        }
+
+        /// One box around all of the damage, for renderers that can only redraw a single area. The window
+        /// size isn't known here, so check `is_fully_damaged` first.
+        pub fn damaged_area(&self) -> Option<BoundingBox> {
+            self.damage.iter().copied().reduce(|a, b| {
+                let (x, y) = (a.x.min(b.x), a.y.min(b.y));
+                BoundingBox {
+                    x,
+                    y,
+                    width: (a.x + a.width).max(b.x + b.width) - x,
+                    height: (a.y + a.height).max(b.y + b.height) - y
                 }
+            })
+        }
+
+        /// Makes the next `update` damage everything. Things that aren't render commands, like the window
+        /// size or the clear color, aren't compared, so call this when they change.
+        pub fn invalidate(&mut self) {
+            self.invalidated = true;
+        }
+    }
+
+    /// Images can be drawn outside of their element, so they damage both.
+    fn drawn_area(command: &RenderCommand) -> BoundingBox {
+        let bounding_box = command.bounding_box;
+        let RenderData::ImageData(data) = &command.render_data else { return bounding_box };
+
+        let draw_box = data.draw_box;
+        let (x, y) = (bounding_box.x.min(draw_box.x), bounding_box.y.min(draw_box.y));
+        BoundingBox {
+            x,
+            y,
+            width: (bounding_box.x + bounding_box.width).max(draw_box.x + draw_box.width) - x,
+            height: (bounding_box.y + bounding_box.height).max(draw_box.y + draw_box.height) - y
+        }
+    }
+
+    fn hash_render_command(command: &RenderCommand) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        let hash_floats = |hasher: &mut DefaultHasher, floats: &[f32]| floats.iter().for_each(|float| float.to_bits().hash(hasher));
+        let hash_box = |hasher: &mut DefaultHasher, bounding_box: BoundingBox| {
+            hash_floats(hasher, &[bounding_box.x, bounding_box.y, bounding_box.width, bounding_box.height])
+        };
+
+        hash_box(&mut hasher, command.bounding_box);
+
+        match &command.render_data {
+            RenderData::NoType => 0u8.hash(&mut hasher),
+            RenderData::RectangleData(data) => {
+                1u8.hash(&mut hasher);
+                data.color.as_u32().hash(&mut hasher);
+                data.stroke_color.as_u32().hash(&mut hasher);
+                let radius = data.corner_radius;
+                hash_floats(&mut hasher, &[radius.top_right, radius.bottom_right, radius.bottom_left, radius.top_left]);
+                let border = data.border_width;
+                [border.top, border.right, border.bottom, border.left].hash(&mut hasher);
+            },
+            RenderData::TextData(data) => {
+                2u8.hash(&mut hasher);
+                let bitmap = data.bitmap;
+                bitmap.texture.hash(&mut hasher);
+                hash_floats(&mut hasher, &[bitmap.texture_size.x, bitmap.texture_size.y, bitmap.texture_offset.x, bitmap.texture_offset.y]);
+                hash_floats(&mut hasher, &[bitmap.cell_size.x, bitmap.cell_size.y, data.line_height]);
+                bitmap.character_list.hash(&mut hasher);
+                bitmap.characters_per_row.hash(&mut hasher);
+                data.text.hash(&mut hasher);
+                data.font_size.hash(&mut hasher);
+                data.split_indices.hash(&mut hasher);
+            },
+            RenderData::ImageData(data) => {
+                3u8.hash(&mut hasher);
+                data.texture_id.hash(&mut hasher);
+                hash_box(&mut hasher, data.draw_box);
+                hash_floats(&mut hasher, &[data.uv_pos.x, data.uv_pos.y, data.uv_size.x, data.uv_size.y]);
+            },
+            RenderData::NineSliceData(data) => {
+                4u8.hash(&mut hasher);
+                data.texture_id.hash(&mut hasher);
+                hash_floats(&mut hasher, &data.x_edges);
+                hash_floats(&mut hasher, &data.y_edges);
+                hash_floats(&mut hasher, &data.u_edges);
+                hash_floats(&mut hasher, &data.v_edges);
             }
+        }
 
-            let mut distance_to_add = parent_size - padding as f32 - child_gap as f32 - inner_content_size;
+        hasher.finish()
+    }
 
-            for child_index in self.layout_elements[current_index].child_elements.clone() {
-                if !aligning_along_axis {
-                    distance_to_add =
-                        if left_to_right {
-                            parent_size - padding as f32 - self.layout_elements[child_index].element.final_size_x
-                        } else {
-                            parent_size - padding as f32 - self.layout_elements[child_index].element.final_size_y
-                        };
+    ///////////////////////////////////////////////
+    //////////////// Validation ///////////////////
+    ///////////////////////////////////////////////
+
+    /// Points at an element in a `LayoutIssue`.
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub struct ElementInfo {
+        pub index: usize,
+        pub id: Option<&'static str>,
+        /// Where the element was opened
+        pub location: Option<&'static Location<'static>>
+    }
+
+    impl fmt::Display for ElementInfo {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "element #{}", self.index)?;
+            if let Some(id) = self.id {
+                write!(f, " ({id})")?;
+            }
+            if let Some(location) = self.location {
+                write!(f, " opened at {location}")?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Something wrong with how the tree was built. The layout still gets solved, but probably
+    /// doesn't look like it was meant to.
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub enum LayoutIssue {
+        /// `close_element` was called with only the root element open.
+        ExtraClose { location: &'static Location<'static> },
+        /// The element was never closed, so everything opened after it ended up inside of it.
+        UnclosedElement { element: ElementInfo },
+        /// Text and image elements are sized to fit their content, so children get added onto that size and drawn on
+        /// top of the text or image. Nine-slices are backgrounds, so they can have children.
+        ChildrenOnLeaf { element: ElementInfo, child: ElementInfo },
+        /// A `limit_width` or `limit_height` with a minimum bigger than the maximum.
+        InvalidLimit { element: ElementInfo, dimension: &'static str, min: i32, max: i32 },
+        /// A `font_text` element has a character its font doesn't, which gets left out when it's drawn.
+        MissingCharacter { element: ElementInfo, character: char },
+        /// A `font_text` element's font handle came from a different context, so it isn't drawn.
+        ForeignFont { element: ElementInfo }
+    }
+
+    impl fmt::Display for LayoutIssue {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                LayoutIssue::ExtraClose { location } => write!(f, "close_element at {location} has no open element to close"),
+                LayoutIssue::UnclosedElement { element } => write!(f, "{element} is never closed"),
+                LayoutIssue::ChildrenOnLeaf { element, child } => write!(f, "{element} can't have children, but {child} is inside of it"),
+                LayoutIssue::InvalidLimit { element, dimension, min, max } => write!(f, "{element} has a {dimension} limit with a minimum of {min} above its maximum of {max}"),
+                LayoutIssue::MissingCharacter { element, character } => write!(f, "{element} has the character {character:?}, which isn't in its font"),
+                LayoutIssue::ForeignFont { element } => write!(f, "{element} uses a font handle from a different context")
+            }
+        }
+    }
+
+    impl CatplushContext<'_> {
+        /// Checks the tree built so far for unbalanced opens and closes, children inside elements that
+        /// can't have any, limits that contradict themselves and text that its font can't draw.
+        /// `end_layout` logs these as warnings.
+        pub fn validate(&self) -> Vec<LayoutIssue> {
+            let mut issues = self.build_issues.clone();
+
+            for &index in self.open_layout_elements.iter().skip(1) {
+                issues.push(LayoutIssue::UnclosedElement { element: self.element_info(index) });
+            }
+
+            for (index, node) in self.layout_elements.iter().enumerate() {
+                let element = &node.element;
+
+                let is_leaf = matches!(element.object_type, ObjectType::Text(_) | ObjectType::Image(_));
+                if is_leaf {
+                    for child in self.children(index) {
+                        issues.push(LayoutIssue::ChildrenOnLeaf { element: self.element_info(index), child: self.element_info(child) });
+                    }
                 }
-                if left_to_right {
-                    match self.layout_elements[current_index].element.layout.child_alignment.x {
-                        ChildXAlignment::Left => {},
-                        ChildXAlignment::Center => {
-                            self.layout_elements[child_index].element.final_pos_x += distance_to_add/2.0
-                        },
-                        ChildXAlignment::Right => {
-                            self.layout_elements[child_index].element.final_pos_x += distance_to_add
+
+                if let ObjectType::Text(CatplushTextData { font: TextFont::Handle(font), text, .. }) = &element.object_type {
+                    match self.font(*font) {
+                        Some(font) => {
+                            if let Some(character) = text.chars().find(|&character| !font.contains(character)) {
+                                issues.push(LayoutIssue::MissingCharacter { element: self.element_info(index), character });
+                            }
                         },
+                        None => issues.push(LayoutIssue::ForeignFont { element: self.element_info(index) })
                     }
-                } else {
-                    match self.layout_elements[current_index].element.layout.child_alignment.y {
-                        ChildYAlignment::Top => {},
-                        ChildYAlignment::Center => {
-                            self.layout_elements[child_index].element.final_pos_y += distance_to_add/2.0;
-                        },
-                        ChildYAlignment::Bottom => {
-                            self.layout_elements[child_index].element.final_pos_y += distance_to_add;
-                        },
+                }
+
+                let limits = &element.layout.size_constraints;
+                for (dimension, limit) in [("width", limits.width), ("height", limits.height)] {
+                    if limit.min > limit.max {
+                        issues.push(LayoutIssue::InvalidLimit { element: self.element_info(index), dimension, min: limit.min, max: limit.max });
                     }
                 }
             }
 
-            for child in self.layout_elements[current_index].child_elements.clone() {
-                self.position_along_axis(left_to_right, child);
-            }
+            issues
         }
 
-        // Solves all sizing and positioning and returns a set of render commands for passing to the
-        // renderer
-        pub fn end_layout(mut self) -> Vec<RenderCommand> {
-            self.open_layout_elements.clear();
+        fn element_info(&self, index: usize) -> ElementInfo {
+            let node = &self.layout_elements[index];
+            ElementInfo { index, id: node.element.id, location: node.location }
+        }
+    }
 
-            self.size_all();
-            self.position_all();
+    /////////////////////////////////////////////////
+    //////////////// Layout Dump ////////////////////
+    /////////////////////////////////////////////////
+
+    /// Everything about a solved layout, for when it looks wrong and you want to see why. With the
+    /// `json` or `ron` features it can be written out and sent to someone else to load and look at.
+    #[derive(Clone, PartialEq, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct LayoutDump {
+        /// In the order they were opened. The first one is the root element made by `begin_layout`.
+        pub nodes: Vec<NodeDump>
+    }
+
+    #[derive(Clone, PartialEq, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct NodeDump {
+        pub index: usize,
+        pub parent: Option<usize>,
+        pub children: Vec<usize>,
+        pub id: Option<String>,
+        pub object: ObjectDump,
+
+        pub width: SizingMode,
+        pub height: SizingMode,
+        pub width_limit: SizeLimit,
+        pub height_limit: SizeLimit,
+        pub padding: Padding,
+        pub child_gap: i32,
+        pub layout_direction: ChildLayoutDirection,
+        pub x_alignment: ChildXAlignment,
+        pub y_alignment: ChildYAlignment,
+        pub keep_aspect_ratio: bool,
+        pub grow_elements_unevenly: bool,
+
+        pub color: ObjectColor,
+        pub stroke_color: ObjectColor,
+        pub corner_radius: CornerRadius,
+        pub border_width: BorderWidth,
 
-            let mut render_commands: Vec<RenderCommand> = vec![];
-            for node in &self.layout_elements[1..self.layout_elements.len()] {
+        pub final_pos_x: f32,
+        pub final_pos_y: f32,
+        pub final_size_x: f32,
+        pub final_size_y: f32
+    }
+
+    /// What kind of element a node is. Textures are only written down by their id.
+    #[derive(Clone, PartialEq, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub enum ObjectDump {
+        Unset,
+        Rectangle,
+        Text {
+            text: String,
+            font_size: u32,
+            line_height: f32,
+            split_indices: Vec<u32>,
+            break_on_overflow: bool
+        },
+        Image { texture_id: NonZeroU32, fit: ImageFit },
+        NineSlice { texture_id: NonZeroU32, insets: NineSliceInsets }
+    }
+
+    impl CatplushContext<'_> {
+        pub(crate) fn dump(&self) -> LayoutDump {
+            let nodes = self.layout_elements.iter().enumerate().map(|(index, node)| {
                 let element = &node.element;
-                let bounding_box = BoundingBox { x: element.final_pos_x, y: element.final_pos_y, width: element.final_size_x, height: element.final_size_y };
-                let render_data = match &element.object_type {
-                    ObjectType::Unset => RenderData::NoType,
-                    ObjectType::Rectangle => {
-                        RenderData::RectangleData(RectangleRenderData { color: element.color, stroke_color: element.stroke_color, corner_radius: element.corner_radius, border_width: element.border_width })
-                    },
-                    ObjectType::Text(data) => {
-                        RenderData::TextData(TextRenderData {
-                            bitmap: data.bitmap.clone(),
-                            text: data.text.clone(),
-                            font_size: data.font_size,
-                            line_height: data.line_height,
-                            split_indices: data.split_indices.clone()
-                        })
+                let layout = &element.layout;
+
+                let object = match &element.object_type {
+                    ObjectType::Unset => ObjectDump::Unset,
+                    ObjectType::Rectangle => ObjectDump::Rectangle,
+                    ObjectType::Text(data) => ObjectDump::Text {
+                        text: data.text.clone(),
+                        font_size: data.font_size,
+                        line_height: data.line_height,
+                        split_indices: data.split_indices.clone(),
+                        break_on_overflow: data.break_on_overflow
                     },
-                    ObjectType::Image(data) => {
-                        RenderData::ImageData(TextureRenderData { texture_id: data.texture_id, width: data.width, height: data.height })
-                    }
+                    ObjectType::Image(data) => ObjectDump::Image { texture_id: data.texture.texture_id, fit: data.fit },
+                    ObjectType::NineSlice(data) => ObjectDump::NineSlice { texture_id: data.texture.texture_id, insets: data.insets }
                 };
 
-                let id = element.id.unwrap_or_default();
-
-                render_commands.push( RenderCommand { bounding_box, render_data, id } );
-            }
+                NodeDump {
+                    index,
+                    parent: node.parent,
+                    children: self.children(index).collect(),
+                    id: element.id.map(str::to_string),
+                    object,
+
+                    width: layout.sizing.width,
+                    height: layout.sizing.height,
+                    width_limit: layout.size_constraints.width,
+                    height_limit: layout.size_constraints.height,
+                    padding: layout.padding,
+                    child_gap: layout.child_gap,
+                    layout_direction: layout.layout_direction,
+                    x_alignment: layout.child_alignment.x,
+                    y_alignment: layout.child_alignment.y,
+                    keep_aspect_ratio: layout.keep_aspect_ratio,
+                    grow_elements_unevenly: layout.grow_elements_unevenly,
+
+                    color: element.color,
+                    stroke_color: element.stroke_color,
+                    corner_radius: element.corner_radius,
+                    border_width: element.border_width,
+
+                    final_pos_x: element.final_pos_x,
+                    final_pos_y: element.final_pos_y,
+                    final_size_x: element.final_size_x,
+                    final_size_y: element.final_size_y
+                }
+            }).collect();
 
-            render_commands
+            LayoutDump { nodes }
         }
     }
 
-    /////////////////////////////////////////////////////
-    ////////////////  Render Structures  ////////////////
-    /////////////////////////////////////////////////////
+    impl LayoutDump {
+        #[cfg(feature = "json")]
+        pub fn to_json(&self) -> Result<String, CatplushError> {
+            serde_json::to_string_pretty(self).map_err(|error| CatplushError::Dump(Box::new(error)))
+        }
 
-    #[derive(Copy, Clone)]
-    pub(crate) struct BoundingBox {
-        pub(crate) x: f32,
-        pub(crate) y: f32,
-        pub(crate) width: f32,
-        pub(crate) height: f32
-    }
+        #[cfg(feature = "json")]
+        pub fn from_json(json: &str) -> Result<Self, CatplushError> {
+            serde_json::from_str(json).map_err(|error| CatplushError::Dump(Box::new(error)))
+        }
+
+        #[cfg(feature = "ron")]
+        pub fn to_ron(&self) -> Result<String, CatplushError> {
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(|error| CatplushError::Dump(Box::new(error)))
+        }
 
-    pub(crate) struct RectangleRenderData {
-        pub(crate) color: ObjectColor,
-        pub(crate) stroke_color: ObjectColor,
-        pub(crate) corner_radius: CornerRadius,
-        pub(crate) border_width: BorderWidth
+        #[cfg(feature = "ron")]
+        pub fn from_ron(ron: &str) -> Result<Self, CatplushError> {
+            ron::from_str(ron).map_err(|error| CatplushError::Dump(Box::new(error)))
+        }
     }
 
+    ////////////////////////////////////////////
+    //////////////// Errors ////////////////////
+    ////////////////////////////////////////////
+
+    #[derive(Debug)]
+    pub enum CatplushError {
+        /// The image data couldn't be decoded, or its format couldn't be worked out.
+        ImageDecode(Box<dyn Error + Send + Sync>),
+        /// The image couldn't be encoded or written out.
+        ImageEncode(Box<dyn Error + Send + Sync>),
+        /// The image has a width or height of zero.
+        EmptyImage,
+        /// Raw pixel data doesn't have the right length for its width and height.
+        InvalidPixelData { expected: usize, actual: usize },
+        /// A layout dump couldn't be written out or read back in.
+        Dump(Box<dyn Error + Send + Sync>),
+        /// A markup file has a mistake in it. Lines and columns start at 1.
+        Markup { line: usize, column: usize, message: String },
+        /// A file couldn't be read.
+        Io(std::io::Error)
+    }
 
-    pub(crate) struct TextRenderData {
-        pub(crate) bitmap: BitmapConfiguration,
-        pub(crate) text: String,
-        pub(crate) font_size: u32,
-        pub(crate) line_height: f32,
-        pub(crate) split_indices: Vec<u32>
+    impl fmt::Display for CatplushError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                CatplushError::ImageDecode(error) => write!(f, "failed to decode image: {error}"),
+                CatplushError::ImageEncode(error) => write!(f, "failed to encode image: {error}"),
+                CatplushError::EmptyImage => write!(f, "image has no pixels"),
+                CatplushError::InvalidPixelData { expected, actual } => write!(f, "expected {expected} bytes of RGBA8 pixel data, got {actual}"),
+                CatplushError::Dump(error) => write!(f, "failed to read or write layout dump: {error}"),
+                CatplushError::Markup { line, column, message } => write!(f, "markup error at {line}:{column}: {message}"),
+                // The io error itself is the source, so it isn't repeated here
+                CatplushError::Io(_) => write!(f, "failed to read file")
+            }
+        }
     }
 
-    pub(crate) struct TextureRenderData {
-        pub(crate) texture_id: NonZeroU32,
-        pub(crate) width: i32,
-        pub(crate) height: i32,
+    impl Error for CatplushError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            match self {
+                CatplushError::ImageDecode(error) | CatplushError::ImageEncode(error) | CatplushError::Dump(error) => Some(error.as_ref()),
+                CatplushError::Io(error) => Some(error),
+                CatplushError::EmptyImage | CatplushError::InvalidPixelData { .. } | CatplushError::Markup { .. } => None
+            }
+        }
+    }
 
-        // Need to implement these myself.
-        // pub(crate) tint: ObjectColor,
-        // pub(crate) corner_radius: CornerRadius
+    impl From<std::io::Error> for CatplushError {
+        fn from(error: std::io::Error) -> Self {
+            CatplushError::Io(error)
+        }
     }
 
-    pub(crate) enum RenderData {
-        NoType,
-        RectangleData(RectangleRenderData),
-        TextData(TextRenderData),
-        ImageData(TextureRenderData)
+    #[cfg(feature = "image")]
+    impl From<image::ImageError> for CatplushError {
+        fn from(error: image::ImageError) -> Self {
+            CatplushError::ImageDecode(Box::new(error))
+        }
     }
 
-    pub struct RenderCommand {
-        pub(crate) bounding_box: BoundingBox,
+    /// Decodes an image, working out its format from the data.
+    #[cfg(feature = "image")]
+    pub fn decode_image(image_data: &[u8]) -> Result<image::DynamicImage, CatplushError> {
+        let image = image::load_from_memory(image_data)?;
 
-        pub(crate) render_data: RenderData,
+        if image.width() == 0 || image.height() == 0 {
+            return Err(CatplushError::EmptyImage);
+        }
 
-        pub id: &'static str,
+        Ok(image)
     }
 
     #[derive(Clone)]
     /// The character list is what actually tells the code how to find each character in the image.
     /// If any characters are missing, or some are included that are not in the bitmap, weird things(and crashes)
     /// will happen.
+    /// Use `from_texture` to make one. Struct literals also have to set `texture_offset` (`Vec2::ZERO` if the bitmap
+    /// has a texture to itself).
     pub struct BitmapConfiguration {
         pub texture: NonZeroU32,
         pub texture_size: Vec2,
+        /// Where the bitmap starts inside of the texture in pixels, for bitmaps that are packed into an atlas.
+        pub texture_offset: Vec2,
         pub cell_size: Vec2,
         pub character_list: String,
         pub characters_per_row: u8
     }
+
+    impl BitmapConfiguration {
+        /// Returns the uv position and size of a character's cell, or `None` if it isn't in the character list.
+        pub fn glyph_uv(&self, character: char) -> Option<(Vec2, Vec2)> {
+            let index_in_bitmap = self.character_list.chars().position(|listed| listed == character)?;
+            Some(self.cell_uv(index_in_bitmap as u32))
+        }
+
+        /// The uv position and size of the cell at this index in the character list.
+        pub(crate) fn cell_uv(&self, index_in_bitmap: u32) -> (Vec2, Vec2) {
+            let uv_cell_size = self.cell_size / self.texture_size;
+            let uv_offset = self.texture_offset / self.texture_size;
+            let x = uv_offset.x + (index_in_bitmap % self.characters_per_row as u32) as f32 * uv_cell_size.x;
+            let y = uv_offset.y + (index_in_bitmap / self.characters_per_row as u32) as f32 * uv_cell_size.y;
+
+            (Vec2::new(x, y), uv_cell_size)
+        }
+
+        /// Makes a bitmap configuration out of a texture, which can be a sub-rectangle of a bigger texture.
+        pub fn from_texture(texture: CatplushTextureData, cell_size: Vec2, character_list: &str, characters_per_row: u8) -> Self {
+            let texture_size = Vec2::new(texture.width as f32, texture.height as f32) / texture.uv_size;
+
+            BitmapConfiguration {
+                texture: texture.texture_id,
+                texture_size,
+                texture_offset: texture.uv_pos * texture_size,
+                cell_size,
+                character_list: character_list.to_owned(),
+                characters_per_row
+            }
+        }
+    }
+
+    /// A bitmap font with every glyph's uvs worked out ahead of time, so drawing text doesn't have to
+    /// search the character list. Add one to the context with `add_font`.
+    pub struct Font {
+        bitmap: BitmapConfiguration,
+        /// Indexed by the character for ASCII, which is most text
+        ascii_glyphs: [Option<(Vec2, Vec2)>; 128],
+        other_glyphs: HashMap<char, (Vec2, Vec2)>
+    }
+
+    impl Font {
+        pub fn new(bitmap: &BitmapConfiguration) -> Self {
+            let mut font = Font { bitmap: bitmap.clone(), ascii_glyphs: [None; 128], other_glyphs: HashMap::new() };
+
+            for (index, character) in bitmap.character_list.chars().enumerate() {
+                let uv = bitmap.cell_uv(index as u32);
+                match font.ascii_glyphs.get_mut(character as usize) {
+                    Some(glyph) => *glyph = Some(uv),
+                    None => { font.other_glyphs.insert(character, uv); }
+                }
+            }
+
+            font
+        }
+
+        pub fn bitmap(&self) -> &BitmapConfiguration {
+            &self.bitmap
+        }
+
+        /// Returns the uv position and size of a character's cell, or `None` if it isn't in the font.
+        pub fn glyph_uv(&self, character: char) -> Option<(Vec2, Vec2)> {
+            match self.ascii_glyphs.get(character as usize) {
+                Some(glyph) => *glyph,
+                None => self.other_glyphs.get(&character).copied()
+            }
+        }
+
+        pub fn contains(&self, character: char) -> bool {
+            self.glyph_uv(character).is_some()
+        }
+
+        /// How big each character is drawn at this font size.
+        pub fn glyph_size(&self, font_size: u32) -> Vec2 {
+            self.bitmap.cell_size * (font_size as f32 / self.bitmap.cell_size.y)
+        }
+    }
+
+    /// Points at a font added to a context. It's only valid for the context that made it, anywhere
+    /// else its text isn't drawn and `validate` reports it.
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub struct FontHandle {
+        context: u32,
+        index: u32,
+        /// Kept here so text elements can be sized without the context
+        cell_size: Vec2
+    }
+
+    /// The font a text element was made with.
+    #[derive(Clone)]
+    pub(crate) enum TextFont {
+        Bitmap(Arc<BitmapConfiguration>),
+        Handle(FontHandle)
+    }
+
+    impl TextFont {
+        pub(crate) fn cell_size(&self) -> Vec2 {
+            match self {
+                TextFont::Bitmap(bitmap) => bitmap.cell_size,
+                TextFont::Handle(handle) => handle.cell_size
+            }
+        }
+    }
+
+    impl CatplushContext<'_> {
+        /// Adds a font that text elements can share with `UiElement::font_text`. Fonts are kept through
+        /// `reset`, so add them once, not every frame.
+        pub fn add_font(&mut self, bitmap: &BitmapConfiguration) -> FontHandle {
+            self.fonts.push(Font::new(bitmap));
+            FontHandle { context: self.id, index: self.fonts.len() as u32 - 1, cell_size: bitmap.cell_size }
+        }
+
+        /// Returns `None` if the handle came from a different context.
+        pub fn font(&self, font: FontHandle) -> Option<&Font> {
+            find_font(&self.fonts, self.id, font)
+        }
+    }
+
+    fn find_font(fonts: &[Font], context: u32, font: FontHandle) -> Option<&Font> {
+        if font.context != context {
+            return None;
+        }
+        fonts.get(font.index as usize)
+    }
+}
+
+//////////////////////////////////////////////////
+//////////////// Layout Macro ////////////////////
+//////////////////////////////////////////////////
+
+/// Builds a tree of elements without writing out every `open_element` and `close_element`.
+/// `element => { ... }` opens an element with children, `element;` adds one without any, and
+/// `@{ ... }` runs normal code in between, like a loop that uses `catplush!` again.
+///
+/// ```
+/// use catplush::catplush;
+/// use catplush::catplush_main::*;
+///
+/// let mut ui = CatplushContext::begin_layout((800, 600), ChildLayoutDirection::TopToBottom);
+/// catplush!(ui, {
+///     UiElement::new().sizing(SizingMode::Grow, SizingMode::Grow).child_gap(10) => {
+///         UiElement::new().rectangle(ObjectColor::RED, CornerRadius::all(5.0)).sizing(SizingMode::Fixed(100), SizingMode::Grow);
+///         @{
+///             for width in [50, 60] {
+///                 catplush!(ui, { UiElement::new().sizing(SizingMode::Fixed(width), SizingMode::Fixed(20)); });
+///             }
+///         }
+///     }
+/// });
+/// let render_commands = ui.end_layout();
+/// ```
+#[macro_export]
+macro_rules! catplush {
+    ($ui:ident, { $($body:tt)* }) => {
+        $crate::catplush!(@children $ui; $($body)*)
+    };
+
+    (@children $ui:ident;) => {};
+    (@children $ui:ident; @{ $($code:tt)* } $($rest:tt)*) => {
+        { $($code)* }
+        $crate::catplush!(@children $ui; $($rest)*);
+    };
+    (@children $ui:ident; $element:expr => { $($children:tt)* } $($rest:tt)*) => {
+        $ui.open_element($element);
+        $crate::catplush!(@children $ui; $($children)*);
+        $ui.close_element();
+        $crate::catplush!(@children $ui; $($rest)*);
+    };
+    (@children $ui:ident; $element:expr; $($rest:tt)*) => {
+        $ui.open_element($element);
+        $ui.close_element();
+        $crate::catplush!(@children $ui; $($rest)*);
+    };
+    (@children $ui:ident; $element:expr) => {
+        $ui.open_element($element);
+        $ui.close_element();
+    };
 }
 
+#[cfg(feature = "friend")]
 pub mod catplush_friend {
     use crate::catplush_main::*;
     use std::num::NonZeroU32;
     use frienderer::{DrawCommand, Quad, RRect, RawImage, Renderer};
-    use image::{DynamicImage, ImageFormat};
+    use image::{DynamicImage, GenericImage, RgbaImage};
     use glow::{NativeTexture};
     use glam::{Vec2};
 
 
-    pub fn friender_render_all(renderer: &mut Renderer, render_commands: Vec<RenderCommand>) {
-        for render_command in render_commands {
-            match render_command.render_data {
-                RenderData::NoType => {},
-                RenderData::RectangleData(data) => {
-                    renderer.push_draw_command(DrawCommand::RRect(RRect {
-                        pos: Vec2::new(render_command.bounding_box.x, render_command.bounding_box.y),
-                        size: Vec2::new(render_command.bounding_box.width, render_command.bounding_box.height),
-                        border_radius: data.corner_radius.as_vec4(),
-                        border_width: data.border_width.as_vec4(),
-                        fill_color: data.color.as_u32(),
-                        stroke_color: data.stroke_color.as_u32()
-                    }));
-                },
-                RenderData::ImageData(data) => {
-                    renderer.push_draw_command(DrawCommand::TextureQuad(
-                        Quad {
-                            pos: Vec2::new(render_command.bounding_box.x, render_command.bounding_box.y),
-                            size: Vec2::new(data.width as f32, data.height as f32),
-                            origin: Vec2::ZERO,
-                            uv_pos: Vec2::ZERO,
-                            uv_size: Vec2::ONE,
-                            rotation: 0.0
-                        },
-                        NativeTexture(data.texture_id)
-                    ));
-                },
-                RenderData::TextData(mut data) => {
-                    render_text(
-                        renderer,
-                        data.text,
-                        &mut data.split_indices,
-                        Vec2::new(render_command.bounding_box.x, render_command.bounding_box.y),
-                        data.bitmap,
-                        data.font_size,
-                        data.line_height
-                    );
-                }
-            }
-        }
+    /// Draws render commands with frienderer.
+    pub struct FriendererBackend<'a> {
+        renderer: &'a mut Renderer
+    }
 
-        renderer.draw();
+    impl<'a> FriendererBackend<'a> {
+        pub fn new(renderer: &'a mut Renderer) -> Self {
+            FriendererBackend { renderer }
+        }
     }
 
-    pub(crate) fn render_text(renderer: &mut Renderer, text: String, split_indexes: &mut [u32], position: Vec2, bitmap: BitmapConfiguration, font_size: u32, line_height: f32) {
-        split_indexes.sort();
-        let lines = split_multiple_indices(&text, split_indexes);
-        for (i, line) in lines.iter().enumerate() {
-            let line_position = position.y + (bitmap.cell_size.y * line_height) * i as f32 + 1.0;
+    impl RenderBackend for FriendererBackend<'_> {
+        fn draw_rectangle(&mut self, bounding_box: BoundingBox, data: &RectangleRenderData) {
+            self.renderer.push_draw_command(DrawCommand::RRect(RRect {
+                pos: Vec2::new(bounding_box.x, bounding_box.y),
+                size: Vec2::new(bounding_box.width, bounding_box.height),
+                border_radius: data.corner_radius.as_vec4(),
+                border_width: data.border_width.as_vec4(),
+                fill_color: data.color.as_u32(),
+                stroke_color: data.stroke_color.as_u32()
+            }));
+        }
 
-            for (i, char) in line.chars().enumerate() {
-                let scale_factor = font_size as f32 / bitmap.cell_size.y;
-                let index_in_bitmap = bitmap.character_list.find(char).unwrap() as i32;
+        fn draw_text(&mut self, bounding_box: BoundingBox, data: &TextRenderData) {
+            for glyph in data.glyphs(Vec2::new(bounding_box.x, bounding_box.y)) {
+                self.draw_image(bounding_box, &glyph);
+            }
+        }
 
-                let uv_cell_size = bitmap.cell_size / bitmap.texture_size;
-                let x = (index_in_bitmap % bitmap.characters_per_row as i32) as f32 * uv_cell_size.x;
-                let y = (index_in_bitmap / bitmap.characters_per_row as i32) as f32 * uv_cell_size.y;
+        fn draw_image(&mut self, _bounding_box: BoundingBox, data: &TextureRenderData) {
+            self.renderer.push_draw_command(DrawCommand::TextureQuad(
+                Quad {
+                    pos: Vec2::new(data.draw_box.x, data.draw_box.y),
+                    size: Vec2::new(data.draw_box.width, data.draw_box.height),
+                    origin: Vec2::ZERO,
+                    uv_pos: data.uv_pos,
+                    uv_size: data.uv_size,
+                    rotation: 0.0
+                },
+                NativeTexture(data.texture_id)
+            ));
+        }
 
-                renderer.push_draw_command(DrawCommand::TextureQuad(
-                    Quad {
-                        pos: Vec2::new(position.x + ((i as f32 * bitmap.cell_size.x) * scale_factor), line_position),
-                        size: bitmap.cell_size * scale_factor,
-                        origin: Vec2::ZERO,
-                        uv_pos: Vec2::new(x, y),
-                        uv_size: uv_cell_size,
-                        rotation: 0.0
-                    },
-                    NativeTexture(bitmap.texture)
-                ));
-            }
+        fn finish(&mut self) {
+            self.renderer.draw();
         }
     }
 
+    pub fn friender_render_all(renderer: &mut Renderer, render_commands: Vec<RenderCommand>) {
+        FriendererBackend::new(renderer).render(&render_commands);
+    }
+
+    /// Uploads the image as RGBA8, converting it first if it is in any other pixel format.
     pub fn load_texture_from_image(renderer: &mut Renderer, image: &DynamicImage) -> NativeTexture {
+        let image = image.to_rgba8();
+
         renderer.upload_texture(RawImage {
             width: image.width(),
             height: image.height(),
-            pixels: image.as_bytes()
+            pixels: image.as_raw()
         })
     }
 
     pub fn get_texture_id(texture: &NativeTexture) -> NonZeroU32 { texture.0 }
 
-    pub fn load_frienderer_texture(renderer: &mut Renderer, image_data: &[u8], format: ImageFormat) -> CatplushTextureData {
-        let image = image::load_from_memory_with_format(image_data, format).unwrap();
-        let texture = renderer.upload_texture(RawImage {
-            width: image.width(),
-            height: image.height(),
-            pixels: image.as_bytes()
-        }).0;
+    /// Decodes and uploads an image. Any format the `image` crate was built with will work.
+    pub fn load_frienderer_texture(renderer: &mut Renderer, image_data: &[u8]) -> Result<CatplushTextureData, CatplushError> {
+        let image = decode_image(image_data)?;
+        let texture = load_texture_from_image(renderer, &image).0;
+
+        Ok(CatplushTextureData::new(texture, image.width() as i32, image.height() as i32))
+    }
+
+    ///////////////////////////////////////////////////
+    //////////////// Texture Atlases //////////////////
+    ///////////////////////////////////////////////////
+
+    /// Refers to an image added to a `TextureAtlasBuilder`. Use it with `TextureAtlas::get` once the
+    /// atlas is built.
+    #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+    pub struct AtlasEntry(usize);
+
+    /// Collects decoded images and packs them into as few textures as possible, so drawing them
+    /// doesn't need to switch between lots of different textures.
+    pub struct TextureAtlasBuilder {
+        page_size: u32,
+        padding: u32,
+        images: Vec<RgbaImage>
+    }
+
+    impl TextureAtlasBuilder {
+        /// Pages are `page_size` pixels wide and at most `page_size` pixels tall. Images bigger than that
+        /// get a page of their own.
+        pub fn new(page_size: u32) -> Self {
+            TextureAtlasBuilder { page_size, padding: 1, images: vec![] }
+        }
+
+        /// Sets the empty space left around each image so they don't bleed into each other when
+        /// filtered. Defaults to 1 pixel.
+        pub fn padding(mut self, padding: u32) -> Self {
+            self.padding = padding;
+            self
+        }
+
+        /// Adds an image to the atlas. Bitmap fonts can be added too and used with `BitmapConfiguration::from_texture`.
+        pub fn add_image(&mut self, image: &DynamicImage) -> AtlasEntry {
+            self.images.push(image.to_rgba8());
+            AtlasEntry(self.images.len() - 1)
+        }
+
+        /// Decodes an image and adds it to the atlas.
+        pub fn add_image_bytes(&mut self, image_data: &[u8]) -> Result<AtlasEntry, CatplushError> {
+            Ok(self.add_image(&decode_image(image_data)?))
+        }
+
+        /// Packs every image into pages and uploads them.
+        pub fn build(self, renderer: &mut Renderer) -> TextureAtlas {
+            self.build_with(|page| renderer.upload_texture(RawImage {
+                width: page.width(),
+                height: page.height(),
+                pixels: page.as_raw()
+            }).0)
+        }
+
+        /// Packs every image into pages and hands each page to `upload`, which returns its texture id.
+        pub fn build_with(self, mut upload: impl FnMut(&RgbaImage) -> NonZeroU32) -> TextureAtlas {
+            let mut placements = self.pack();
+
+            let mut page_sizes: Vec<(u32, u32)> = vec![];
+            for placement in &placements {
+                let image = &self.images[placement.image];
+                if page_sizes.len() <= placement.page {
+                    page_sizes.resize(placement.page + 1, (0, 0));
+                }
+
+                let page_size = &mut page_sizes[placement.page];
+                page_size.0 = u32::max(page_size.0, placement.x + image.width());
+                page_size.1 = u32::max(page_size.1, placement.y + image.height());
+            }
 
-        CatplushTextureData {
-            texture_id: texture,
-            width: image.width() as i32,
-            height: image.height() as i32
+            let mut pages: Vec<RgbaImage> = page_sizes.iter().map(|(width, height)| RgbaImage::new(*width, *height)).collect();
+            for placement in &placements {
+                // Can't fail since the pages were sized to fit every placement
+                pages[placement.page].copy_from(&self.images[placement.image], placement.x, placement.y).unwrap();
+            }
+
+            let page_textures: Vec<CatplushTextureData> = pages.iter().map(|page| {
+                CatplushTextureData::new(upload(page), page.width() as i32, page.height() as i32)
+            }).collect();
+
+            placements.sort_by_key(|placement| placement.image);
+
+            let entries = placements.iter().map(|placement| {
+                let image = &self.images[placement.image];
+                page_textures[placement.page].sub_texture(placement.x as i32, placement.y as i32, image.width() as i32, image.height() as i32)
+            }).collect();
+
+            TextureAtlas { pages: page_textures, entries }
+        }
+
+        /// Shelf packing: the tallest images go first and are laid out in rows, starting a new row when
+        /// the current one is full and a new page when the page is full.
+        fn pack(&self) -> Vec<AtlasPlacement> {
+            let mut order: Vec<usize> = (0..self.images.len()).collect();
+            order.sort_by_key(|&index| std::cmp::Reverse(self.images[index].height()));
+
+            let mut placements: Vec<AtlasPlacement> = vec![];
+            let mut oversized: Vec<usize> = vec![];
+            let mut page = 0;
+            let mut page_used = false;
+            let (mut shelf_x, mut shelf_y, mut shelf_height) = (0, 0, 0);
+
+            for index in order {
+                let width = self.images[index].width() + self.padding;
+                let height = self.images[index].height() + self.padding;
+
+                if width > self.page_size || height > self.page_size {
+                    oversized.push(index);
+                    continue;
+                }
+
+                if shelf_x + width > self.page_size {
+                    shelf_y += shelf_height;
+                    (shelf_x, shelf_height) = (0, 0);
+                }
+
+                if shelf_y + height > self.page_size {
+                    page += 1;
+                    (shelf_x, shelf_y, shelf_height) = (0, 0, 0);
+                }
+
+                placements.push(AtlasPlacement { image: index, page, x: shelf_x, y: shelf_y });
+                page_used = true;
+                shelf_x += width;
+                shelf_height = u32::max(shelf_height, height);
+            }
+
+            // Too big to share a page with anything else, so they go after the shared pages
+            let first_own_page = if page_used { page + 1 } else { page };
+            for (own_page, index) in (first_own_page..).zip(oversized) {
+                placements.push(AtlasPlacement { image: index, page: own_page, x: 0, y: 0 });
+            }
+
+            placements
+        }
+    }
+
+    struct AtlasPlacement {
+        image: usize,
+        page: usize,
+        x: u32,
+        y: u32
+    }
+
+    /// Images packed into one or more textures by a `TextureAtlasBuilder`.
+    pub struct TextureAtlas {
+        pages: Vec<CatplushTextureData>,
+        entries: Vec<CatplushTextureData>
+    }
+
+    impl TextureAtlas {
+        /// Returns the part of the atlas holding the image. It can be used like any other texture,
+        /// including in `SpriteSheet`s.
+        pub fn get(&self, entry: AtlasEntry) -> CatplushTextureData {
+            self.entries[entry.0]
+        }
+
+        /// The textures the images were packed into.
+        pub fn pages(&self) -> &[CatplushTextureData] {
+            &self.pages
         }
     }
 }
+
+pub mod catplush_software;
+
+mod catplush_export;
+
+pub mod catplush_svg;
+
+pub mod catplush_tui;
+
+pub mod catplush_html;
+
+pub mod catplush_markup;
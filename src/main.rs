@@ -1,9 +1,9 @@
+use catplush::catplush;
 use catplush::catplush_main::*;
 use catplush::catplush_friend::*;
 use frienderer::{Renderer};
 use glam::{ivec2, Vec2};
 use glfw::{Action, Context, Key, OpenGlProfileHint, WindowHint};
-use image::ImageFormat;
 
 struct CardColor;
 
@@ -15,11 +15,10 @@ impl CardColor {
     pub const PURPLE: ObjectColor = ObjectColor::from_u32_hex(0x69418eff);
 }
 
-fn topbar_button(button_width: i32) -> UiElement {
+fn topbar_button(style: &Style, button_width: i32) -> UiElement {
     UiElement::new()
-        .rectangle(ObjectColor(51, 136, 175, 255), CornerRadius::all(10.0))
+        .style(style)
         .sizing(SizingMode::Fixed(button_width), SizingMode::Grow)
-        .alignment(ChildXAlignment::Center, ChildYAlignment::Center)
 }
 
 fn spacer() -> UiElement {
@@ -27,43 +26,33 @@ fn spacer() -> UiElement {
         .sizing(SizingMode::Grow, SizingMode::Grow)
 }
 
-fn sidebar_element() -> UiElement {
+fn sidebar_element(theme: &Theme) -> UiElement {
     UiElement::new()
-        .rectangle(ObjectColor(51, 136, 175, 255), CornerRadius::all(10.0))
+        .style(theme.style("button"))
         .sizing(SizingMode::Grow, SizingMode::Fixed(50))
         .padding(Padding::all(20))
 }
 
-fn card_cell(ui: &mut CatplushContext, bitmap: &BitmapConfiguration, card_name: &str, card_icon: CatplushTextureData, card_color: ObjectColor) {
-    ui.open_element(UiElement::new()
-        .rectangle(card_color, CornerRadius::all(10.0))
-        .sizing(SizingMode::Grow, SizingMode::Fixed(40))
-        .padding(Padding::new(10, 15, 5, 5))
-        .alignment(ChildXAlignment::Left, ChildYAlignment::Center)
-        .child_gap(5));
-
-        ui.open_element(UiElement::new()
-            .image(card_icon, None, None, false));
-        ui.close_element();
-
-        ui.open_element(spacer());
-        ui.close_element();
-
-        ui.open_element(UiElement::new()
-            .text(bitmap, card_name, 17, 1.2));
-        ui.close_element();
-
-    ui.close_element();
+fn card_cell(ui: &mut CatplushContext, font: FontHandle, card_name: &str, card_icon: CatplushTextureData, card_color: ObjectColor) {
+    catplush!(ui, {
+        UiElement::new()
+            .rectangle(card_color, CornerRadius::all(10.0))
+            .sizing(SizingMode::Grow, SizingMode::Fixed(40))
+            .padding(Padding::new(10, 15, 5, 5))
+            .alignment(ChildXAlignment::Left, ChildYAlignment::Center)
+            .child_gap(5) => {
+
+            UiElement::new().image(card_icon, None, None, false);
+            spacer();
+            UiElement::new().font_text(font, card_name, 17, 1.2);
+        }
+    });
 }
 
 const CONTRAST_HIGHLIGHT: ObjectColor = ObjectColor::from_u32_hex(0xeebe2bff);
-const MONOCHROME_HIGHLIGHT: ObjectColor = ObjectColor::from_u32_hex(0x7dd1eeff);
-const DARK_BORDER: ObjectColor = ObjectColor::from_u32_hex(0x213d4dff);
 
-const WATCHER_RARE_SKILL_IMAGE: &[u8] = include_bytes!("../resources/CardIcon_Watcher_Skill_Rare.png");
-const IRONCLAD_UNCOMMON_POWER_IMAGE: &[u8] = include_bytes!("../resources/CardIcon_Ironclad_Power_Uncommon.png");
-const DEFECT_RARE_ATTACK_IMAGE: &[u8] = include_bytes!("../resources/CardIcon_Defect_Attack_Rare.png");
-const SILENT_COMMON_ATTACK_IMAGE: &[u8] = include_bytes!("../resources/CardIcon_Silent_Attack_Common.png");
+// Watcher skill, Ironclad power, Defect attack and Silent attack, each 32x32
+const CARD_ICONS_IMAGE: &[u8] = include_bytes!("../resources/CardIcons.png");
 const AWAKENED_IMAGE: &[u8] = include_bytes!("../resources/Achv-Awakened.png");
 
 const UIUA_BITMAP: &[u8] = include_bytes!("../resources/Uiua386.png");
@@ -96,175 +85,206 @@ fn main() {
 	};
 
 	let mut renderer = Renderer::new(viewport, gl);
-	renderer.set_clear_color(0.0, 0.0, 0.0, 0.5);
-
-	let watcher_rare_skill_image = load_frienderer_texture(&mut renderer, WATCHER_RARE_SKILL_IMAGE, ImageFormat::Png);
-	let ironclad_uncommon_power_image = load_frienderer_texture(&mut renderer, IRONCLAD_UNCOMMON_POWER_IMAGE, ImageFormat::Png);
-	let defect_rare_attack_image = load_frienderer_texture(&mut renderer, DEFECT_RARE_ATTACK_IMAGE, ImageFormat::Png);
-	let silent_common_attack_image = load_frienderer_texture(&mut renderer, SILENT_COMMON_ATTACK_IMAGE, ImageFormat::Png);
-
-	let awakened_image = load_frienderer_texture(&mut renderer, AWAKENED_IMAGE, ImageFormat::Png);
-
-	let uiua_bitmap_texture = load_frienderer_texture(&mut renderer, UIUA_BITMAP, ImageFormat::Png);
-	let uiua_bitmap = BitmapConfiguration {
-	    texture: uiua_bitmap_texture.texture_id,
-		texture_size: Vec2::new(uiua_bitmap_texture.width as f32, uiua_bitmap_texture.height as f32),
-		cell_size: Vec2::new(15., 24.),
-		character_list: " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~".to_owned(),
-		characters_per_row: 19
-	};
+
+	// Everything goes into one atlas so the whole ui can be drawn from a single texture
+	let mut atlas_builder = TextureAtlasBuilder::new(512);
+	let card_icons_entry = atlas_builder.add_image_bytes(CARD_ICONS_IMAGE).expect("Failed to load the card icons.");
+	let awakened_entry = atlas_builder.add_image_bytes(AWAKENED_IMAGE).expect("Failed to load the awakened icon.");
+	let uiua_bitmap_entry = atlas_builder.add_image_bytes(UIUA_BITMAP).expect("Failed to load the Uiua bitmap font.");
+	let atlas = atlas_builder.build(&mut renderer);
+
+	let card_icons = SpriteSheet::grid(atlas.get(card_icons_entry), 32, 32)
+		.name_frame("watcher_rare_skill", 0)
+		.name_frame("ironclad_uncommon_power", 1)
+		.name_frame("defect_rare_attack", 2)
+		.name_frame("silent_common_attack", 3);
+
+	let watcher_rare_skill_image = card_icons.named("watcher_rare_skill").unwrap();
+	let ironclad_uncommon_power_image = card_icons.named("ironclad_uncommon_power").unwrap();
+	let defect_rare_attack_image = card_icons.named("defect_rare_attack").unwrap();
+	let silent_common_attack_image = card_icons.named("silent_common_attack").unwrap();
+
+	let awakened_image = atlas.get(awakened_entry);
+
+	let uiua_bitmap = BitmapConfiguration::from_texture(
+		atlas.get(uiua_bitmap_entry),
+		Vec2::new(15., 24.),
+		" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~",
+		19
+	);
+
+	let themes = [
+		Theme::dark().with_font(&uiua_bitmap, 16, 1.2),
+		Theme::light().with_font(&uiua_bitmap, 16, 1.2)
+	];
+
+    // F1 toggles the layout inspector and T switches between the dark and light themes
+    let mut show_debug_view = false;
+    let mut theme_index = 0;
+
+    // Kept between frames so its memory gets reused
+    let mut ui = CatplushContext::new();
+    ui.set_theme(&themes[theme_index]);
+    let uiua_font = ui.add_font(&uiua_bitmap);
+
+    // Nothing is drawn when the ui looks the same as last frame
+    let mut damage_tracker = DamageTracker::new();
+    let mut window_size = window.get_size();
 
     while !window.should_close() {
         glfw.poll_events();
         for (_, event) in glfw::flush_messages(&events) {
-            #[allow(clippy::single_match)]
             match event {
                 glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => window.set_should_close(true),
+                glfw::WindowEvent::Key(Key::F1, _, Action::Press, _) => show_debug_view = !show_debug_view,
+                glfw::WindowEvent::Key(Key::T, _, Action::Press, _) => {
+                    theme_index = (theme_index + 1) % themes.len();
+                    ui.set_theme(&themes[theme_index]);
+                    damage_tracker.invalidate();
+                }
                 _ => {}
             }
         }
-        renderer.resize(window.get_size().0, window.get_size().1, scale_factor);
-
-        let mut ui = CatplushContext::begin_layout(window.get_size(), ChildLayoutDirection::TopToBottom);
-
-        ui.open_element(UiElement::new()
-            .sizing(SizingMode::Grow, SizingMode::Grow)
-            .layout_direction(ChildLayoutDirection::TopToBottom)
-            .padding(Padding::all(10))
-            .child_gap(10));
-
-            ui.open_element(UiElement::new()
-                .rectangle(DARK_BORDER, CornerRadius::all(15.0))
-                .sizing(SizingMode::Grow, SizingMode::Fixed(40))
-                .limit_width(400, 800)
-                .padding(Padding::all(5))
-                .child_gap(10));
-
-                ui.open_element(topbar_button(100)
-                    .border(MONOCHROME_HIGHLIGHT, BorderWidth::all(2)));
-
-                    ui.open_element(UiElement::new()
-                        .text(&uiua_bitmap, "File", 16, 1.2));
-                    ui.close_element();
-
-                ui.close_element();
-
-                ui.open_element(topbar_button(75));
-                    ui.open_element(UiElement::new()
-                        .text(&uiua_bitmap, "Edit", 16, 1.2));
-                    ui.close_element();
-                ui.close_element();
-
-                ui.open_element(spacer());
-                ui.close_element();
+        if window.get_size() != window_size {
+            window_size = window.get_size();
+            damage_tracker.invalidate();
+        }
+        renderer.resize(window_size.0, window_size.1, scale_factor);
 
-                ui.open_element(topbar_button(75));
-                    ui.open_element(UiElement::new()
-                        .text(&uiua_bitmap, "Close", 16, 1.2));
-                    ui.close_element();
-                ui.close_element();
+        let theme = &themes[theme_index];
+        let background = theme.background;
+        renderer.set_clear_color(background.0 as f32 / 255.0, background.1 as f32 / 255.0, background.2 as f32 / 255.0, background.3 as f32 / 255.0);
 
-            ui.close_element();
+        ui.reset(window_size, ChildLayoutDirection::TopToBottom);
 
-            ui.open_element(UiElement::new()
-                .rectangle(ObjectColor(26, 67, 87, 255), CornerRadius::all(20.0))
+        catplush!(ui, {
+            UiElement::new()
                 .sizing(SizingMode::Grow, SizingMode::Grow)
+                .layout_direction(ChildLayoutDirection::TopToBottom)
                 .padding(Padding::all(10))
-                .child_gap(10));
-
-                ui.open_element(UiElement::new()
-                    .rectangle(ObjectColor(17, 36, 46, 255), CornerRadius::all(15.0))
-                    .sizing(SizingMode::Fixed(200), SizingMode::Grow)
-                    .layout_direction(ChildLayoutDirection::TopToBottom)
-                    .alignment(ChildXAlignment::Center, ChildYAlignment::Top)
-                    .padding(Padding::all(10))
-                    .child_gap(10));
-
-                    ui.open_element(sidebar_element()
-                        .alignment(ChildXAlignment::Center, ChildYAlignment::Center));
-
-                        ui.open_element(UiElement::new()
-                            .image(awakened_image, Some(30), None, false));
-                        ui.close_element();
-
-                        ui.open_element(spacer());
-                        ui.close_element();
-
-                        ui.open_element(UiElement::new()
-                            .text(&uiua_bitmap, "Cards", 22, 1.2));
-                        ui.close_element();
-
-                    ui.close_element();
-                    for _ in 1..5 {
-                        ui.open_element(sidebar_element());
-                        ui.close_element();
-                    }
-
-                ui.close_element();
-
-                ui.open_element(UiElement::new()
-                    .rectangle(ObjectColor(17, 36, 46, 255), CornerRadius::all(17.0))
-                    .border(CONTRAST_HIGHLIGHT, BorderWidth::all(3))
-                    .sizing(SizingMode::Grow, SizingMode::Grow)
-                    .alignment(ChildXAlignment::Center, ChildYAlignment::Top)
-                    .layout_direction(ChildLayoutDirection::TopToBottom)
-                    .padding(Padding::all(10))
-                    .child_gap(10));
+                .child_gap(10) => {
 
-                    ui.open_element(UiElement::new()
-                        .sizing(SizingMode::Grow, SizingMode::Fit)
-                        .alignment(ChildXAlignment::Left, ChildYAlignment::Center)
-                        .child_gap(10));
+                UiElement::new()
+                    .style(theme.style("bar"))
+                    .sizing(SizingMode::Grow, SizingMode::Fixed(40))
+                    .limit_width(400, 800) => {
 
-                        card_cell(&mut ui, &uiua_bitmap, "Alpha", watcher_rare_skill_image, CardColor::PURPLE);
-                        card_cell(&mut ui, &uiua_bitmap, "Blasphemy", watcher_rare_skill_image, CardColor::PURPLE);
-
-                        // card_cell(&mut ui, &uiua_bitmap, "Combust", ironclad_uncommon_power_image, CardColor::RED);
-                        // card_cell(&mut ui, &uiua_bitmap, "Evolve", ironclad_uncommon_power_image, CardColor::RED);
-
-                    ui.close_element();
-
-                    ui.open_element(UiElement::new()
-                        .sizing(SizingMode::Grow, SizingMode::Fit)
-                        .alignment(ChildXAlignment::Left, ChildYAlignment::Center)
-                        .grow_elements_unevenly()
-                        .child_gap(10));
-
-                        card_cell(&mut ui, &uiua_bitmap, "Bane", silent_common_attack_image, CardColor::GREEN);
-                        card_cell(&mut ui, &uiua_bitmap, "Dagger Spray", silent_common_attack_image, CardColor::GREEN);
-
-                        // card_cell(&mut ui, &uiua_bitmap, "Thunder Strike", defect_rare_attack_image, CardColor::BLUE);
-                        // card_cell(&mut ui, &uiua_bitmap, "Hyperbeam", defect_rare_attack_image, CardColor::BLUE);
-
-                    ui.close_element();
+                    topbar_button(theme.style("highlighted_button"), 100) => {
+                        UiElement::new().styled_text(theme.style("button"), "File");
+                    }
+                    topbar_button(theme.style("button"), 75) => {
+                        UiElement::new().styled_text(theme.style("button"), "Edit");
+                    }
+                    spacer();
+                    topbar_button(theme.style("button"), 75) => {
+                        UiElement::new().styled_text(theme.style("button"), "Close");
+                    }
+                }
+
+                UiElement::new()
+                    .style(theme.style("panel"))
+                    .sizing(SizingMode::Grow, SizingMode::Grow) => {
+
+                    UiElement::new()
+                        .style(theme.style("surface"))
+                        .sizing(SizingMode::Fixed(200), SizingMode::Grow)
+                        .layout_direction(ChildLayoutDirection::TopToBottom)
+                        .alignment(ChildXAlignment::Center, ChildYAlignment::Top) => {
+
+                        sidebar_element(theme).alignment(ChildXAlignment::Center, ChildYAlignment::Center) => {
+                            UiElement::new().image(awakened_image, Some(30), None, false);
+                            spacer();
+                            UiElement::new().font_text(uiua_font, "Cards", 22, 1.2);
+                        }
+
+                        @{
+                            for (fit, image) in [
+                                (ImageFit::Fill, ironclad_uncommon_power_image),
+                                (ImageFit::Contain, ironclad_uncommon_power_image),
+                                (ImageFit::Cover, defect_rare_attack_image),
+                                (ImageFit::None, defect_rare_attack_image)
+                            ] {
+                                catplush!(ui, {
+                                    sidebar_element(theme).padding(Padding::all(5)) => {
+                                        UiElement::new()
+                                            .image(image, None, None, false)
+                                            .image_fit(fit)
+                                            .sizing(SizingMode::Grow, SizingMode::Grow);
+                                    }
+                                });
+                            }
+                        }
+                    }
 
-                    ui.open_element(UiElement::new()
+                    UiElement::new()
+                        .style(theme.style("surface"))
+                        .border(CONTRAST_HIGHLIGHT, BorderWidth::all(3))
                         .sizing(SizingMode::Grow, SizingMode::Grow)
-                        .padding(Padding::all(10))
-                        .child_gap(10)
-                        .layout_direction(ChildLayoutDirection::TopToBottom));
-
-                        ui.open_element(UiElement::new()
-                            .rectangle(ObjectColor(255, 255, 255, 122), CornerRadius::all(5.0))
-                            .sizing(SizingMode::Fit, SizingMode::Fit));
-
-                            ui.open_element(UiElement::new()
-                                .text(&uiua_bitmap, "Panic Button:\nGain 30 Block.\nYou cannot gain Block from cards for 2 turns.\nExhaust.", 20, 1.2));
-                            ui.close_element();
-
-                        ui.close_element();
-
-                    ui.close_element();
-
-                ui.close_element();
+                        .alignment(ChildXAlignment::Center, ChildYAlignment::Top)
+                        .layout_direction(ChildLayoutDirection::TopToBottom) => {
+
+                        UiElement::new()
+                            .sizing(SizingMode::Grow, SizingMode::Fit)
+                            .alignment(ChildXAlignment::Left, ChildYAlignment::Center)
+                            .child_gap(10) => {
+
+                            @{
+                                card_cell(&mut ui, uiua_font, "Alpha", watcher_rare_skill_image, CardColor::PURPLE);
+                                card_cell(&mut ui, uiua_font, "Blasphemy", watcher_rare_skill_image, CardColor::PURPLE);
+
+                                // card_cell(&mut ui, uiua_font, "Combust", ironclad_uncommon_power_image, CardColor::RED);
+                                // card_cell(&mut ui, uiua_font, "Evolve", ironclad_uncommon_power_image, CardColor::RED);
+                            }
+                        }
+
+                        UiElement::new()
+                            .sizing(SizingMode::Grow, SizingMode::Fit)
+                            .alignment(ChildXAlignment::Left, ChildYAlignment::Center)
+                            .grow_elements_unevenly()
+                            .child_gap(10) => {
+
+                            @{
+                                card_cell(&mut ui, uiua_font, "Bane", silent_common_attack_image, CardColor::GREEN);
+                                card_cell(&mut ui, uiua_font, "Dagger Spray", silent_common_attack_image, CardColor::GREEN);
+
+                                // card_cell(&mut ui, uiua_font, "Thunder Strike", defect_rare_attack_image, CardColor::BLUE);
+                                // card_cell(&mut ui, uiua_font, "Hyperbeam", defect_rare_attack_image, CardColor::BLUE);
+                            }
+                        }
+
+                        UiElement::new()
+                            .sizing(SizingMode::Grow, SizingMode::Grow)
+                            .padding(Padding::all(10))
+                            .child_gap(10)
+                            .layout_direction(ChildLayoutDirection::TopToBottom) => {
+
+                            UiElement::new()
+                                .style(theme.style("note"))
+                                .sizing(SizingMode::Fit, SizingMode::Fit) => {
+
+                                UiElement::new().font_text(uiua_font, "Panic Button:\nGain 30 Block.\nYou cannot gain Block from cards for 2 turns.\nExhaust.", 20, 1.2);
+                            }
+                        }
+                    }
+                }
+            }
+        });
 
-            ui.close_element();
+        if show_debug_view {
+            let (cursor_x, cursor_y) = window.get_cursor_pos();
+            ui.debug_view(&uiua_bitmap, Some((cursor_x as f32, cursor_y as f32)));
+        }
 
-        ui.close_element();
+        ui.solve();
 
-        let render_commands = ui.end_layout();
+        damage_tracker.update(ui.render_commands());
+        if damage_tracker.is_unchanged() {
+            // Swapping buffers is what waits for vsync, so wait here instead
+            glfw.wait_events_timeout(1.0 / 60.0);
+            continue;
+        }
 
-        friender_render_all(&mut renderer, render_commands);
+        FriendererBackend::new(&mut renderer).render(ui.render_commands());
 
         window.swap_buffers();
     }
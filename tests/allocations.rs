@@ -0,0 +1,113 @@
+//! Checks that a context kept between frames stops allocating once it has seen the ui once.
+//!
+//! Text elements own a copy of their text, which gets made every time one is built, so text is
+//! checked separately: building it allocates, but solving and drawing it shouldn't.
+
+use catplush::catplush;
+use catplush::catplush_main::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+mod common;
+use common::test_bitmap;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get().map(|count| count + 1)));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Only counts allocations made on this thread, so other tests running at the same time don't show up.
+fn count_allocations(frame: impl FnOnce()) -> usize {
+    ALLOCATIONS.with(|count| count.set(Some(0)));
+    frame();
+    ALLOCATIONS.with(|count| count.replace(None)).unwrap()
+}
+
+fn frame(ui: &mut CatplushContext) {
+    ui.reset((800, 600), ChildLayoutDirection::TopToBottom);
+
+    catplush!(ui, {
+        UiElement::new().sizing(SizingMode::Grow, SizingMode::Grow).padding(Padding::all(10)).child_gap(5) => {
+            UiElement::new().id("bar").sizing(SizingMode::Grow, SizingMode::Fixed(40)) => {
+                @{
+                    for button in 0..10 {
+                        *ui.state::<u32>(ElementId::indexed("button", button)) += 1;
+                        catplush!(ui, {
+                            UiElement::new()
+                                .id_indexed("button", button)
+                                .rectangle(ObjectColor::WHITE, CornerRadius::all(4.0))
+                                .sizing(SizingMode::Grow, SizingMode::Grow)
+                        });
+                    }
+                }
+            }
+            UiElement::new().rectangle(ObjectColor::BLACK, CornerRadius::default()).sizing(SizingMode::Grow, SizingMode::Grow)
+        }
+    });
+
+    ui.solve();
+    assert_eq!(ui.render_commands().count(), 13);
+}
+
+#[test]
+fn steady_frames_do_not_allocate() {
+    let mut ui = CatplushContext::new();
+    assert!(count_allocations(|| frame(&mut ui)) > 0);
+    frame(&mut ui);
+
+    assert_eq!(count_allocations(|| frame(&mut ui)), 0);
+    assert_eq!(*ui.state::<u32>(ElementId::indexed("button", 3)), 3);
+}
+
+fn text_frame(ui: &mut CatplushContext, font: FontHandle) {
+    ui.reset((800, 600), ChildLayoutDirection::TopToBottom);
+
+    catplush!(ui, {
+        UiElement::new().id("labels").sizing(SizingMode::Grow, SizingMode::Grow).child_gap(5) => {
+            UiElement::new().font_text(font, "File", 20, 1.0);
+            UiElement::new().font_text(font, "Edit", 20, 1.0);
+            UiElement::new().font_text(font, "two\nlines", 20, 1.0)
+        }
+    });
+}
+
+/// Returns how many glyphs were drawn, so the drawing can't be skipped.
+fn draw_text(ui: &CatplushContext) -> usize {
+    ui.render_commands().map(|command| match &command.render_data {
+        RenderData::TextData(text) => text.lines().count() + text.glyphs(glam::Vec2::ZERO).count(),
+        _ => 0
+    }).sum()
+}
+
+#[test]
+fn text_only_allocates_while_building() {
+    let mut ui = CatplushContext::new();
+    let font = ui.add_font(&test_bitmap());
+    for _ in 0..2 {
+        text_frame(&mut ui, font);
+        ui.solve();
+        draw_text(&ui);
+    }
+
+    // One copy of each label's text, plus the new line list of the label that has one
+    assert_eq!(count_allocations(|| text_frame(&mut ui, font)), 4);
+    assert_eq!(count_allocations(|| ui.solve()), 0);
+    let mut glyphs = 0;
+    assert_eq!(count_allocations(|| glyphs = draw_text(&ui)), 0);
+    assert_eq!(glyphs, 4 + 4 + 8 + 4);
+}
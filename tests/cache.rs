@@ -0,0 +1,172 @@
+use catplush::catplush;
+use catplush::catplush_main::*;
+
+mod common;
+use common::test_bitmap;
+
+struct Frame {
+    window_size: (i32, i32),
+    sidebar_items: u32,
+    note: &'static str
+}
+
+const SIDEBAR: ElementId = ElementId::new("sidebar");
+const PANEL: ElementId = ElementId::new("panel");
+
+fn build(ui: &mut CatplushContext, frame: &Frame) {
+    let bitmap = test_bitmap();
+    catplush!(ui, {
+        UiElement::new().sizing(SizingMode::Grow, SizingMode::Grow).padding(Padding::all(10)).child_gap(10) => {
+            UiElement::new()
+                .id("sidebar")
+                .sizing(SizingMode::Fixed(200), SizingMode::Grow)
+                .layout_direction(ChildLayoutDirection::TopToBottom)
+                .padding(Padding::all(5))
+                .child_gap(5) => {
+                @{
+                    for item in 0..frame.sidebar_items {
+                        catplush!(ui, {
+                            UiElement::new().id_indexed("item", item).sizing(SizingMode::Grow, SizingMode::Fixed(30)).padding(Padding::all(5)) => {
+                                UiElement::new().text(&bitmap, "item", 20, 1.0)
+                            }
+                        });
+                    }
+                }
+            }
+            UiElement::new()
+                .id("panel")
+                .sizing(SizingMode::Grow, SizingMode::Grow)
+                .alignment(ChildXAlignment::Center, ChildYAlignment::Center)
+                .padding(Padding::all(20)) => {
+                UiElement::new().text(&bitmap, frame.note, 20, 1.2)
+            }
+        }
+    });
+}
+
+fn layout(render_commands: &[RenderCommand]) -> Vec<(f32, f32, f32, f32, Vec<String>)> {
+    render_commands.iter().map(|command| {
+        let bounding_box = command.bounding_box;
+        let lines = match &command.render_data {
+            RenderData::TextData(text) => text.lines().map(str::to_string).collect(),
+            _ => vec![]
+        };
+        (bounding_box.x, bounding_box.y, bounding_box.width, bounding_box.height, lines)
+    }).collect()
+}
+
+#[test]
+fn cached_layouts_match_fresh_ones() {
+    let frames = [
+        Frame { window_size: (800, 600), sidebar_items: 3, note: "a note" },
+        Frame { window_size: (800, 600), sidebar_items: 3, note: "a note" },
+        // Only the panel gets wider
+        Frame { window_size: (900, 600), sidebar_items: 3, note: "a note" },
+        // Both get shorter
+        Frame { window_size: (900, 500), sidebar_items: 3, note: "a note" },
+        Frame { window_size: (900, 500), sidebar_items: 4, note: "a note" },
+        Frame { window_size: (900, 500), sidebar_items: 4, note: "a much longer note.\nwith two lines" },
+        Frame { window_size: (900, 500), sidebar_items: 4, note: "a much longer note.\nwith two lines" }
+    ];
+    // Whether the sidebar and the panel should reuse their layout in each frame
+    let expected_caching = [
+        (false, false),
+        (true, true),
+        (true, false),
+        (false, false),
+        (false, true),
+        (true, false),
+        (true, true)
+    ];
+
+    let mut ui = CatplushContext::new();
+    for (frame, (sidebar_cached, panel_cached)) in frames.iter().zip(expected_caching) {
+        let mut fresh = CatplushContext::begin_layout(frame.window_size, ChildLayoutDirection::LeftToRight);
+        build(&mut fresh, frame);
+        let expected = layout(&fresh.end_layout());
+
+        ui.reset(frame.window_size, ChildLayoutDirection::LeftToRight);
+        build(&mut ui, frame);
+        ui.solve();
+
+        assert_eq!((ui.is_layout_cached(SIDEBAR), ui.is_layout_cached(PANEL)), (sidebar_cached, panel_cached));
+        assert_eq!(layout(&ui.render_commands().collect::<Vec<_>>()), expected);
+    }
+}
+
+#[test]
+fn caching_can_be_turned_off() {
+    let frame = Frame { window_size: (800, 600), sidebar_items: 3, note: "a note" };
+
+    let mut ui = CatplushContext::new();
+    ui.set_layout_caching(false);
+    for _ in 0..2 {
+        ui.reset(frame.window_size, ChildLayoutDirection::LeftToRight);
+        build(&mut ui, &frame);
+        ui.solve();
+    }
+    assert!(!ui.is_layout_cached(SIDEBAR));
+}
+
+#[test]
+fn duplicate_ids_are_not_cached() {
+    const CARD: ElementId = ElementId::new("card");
+    let bitmap = test_bitmap();
+    let build = |ui: &mut CatplushContext, note: &str, duplicate: bool| {
+        let second_id = if duplicate { "card" } else { "other" };
+        catplush!(ui, {
+            UiElement::new().sizing(SizingMode::Grow, SizingMode::Grow).child_gap(10) => {
+                UiElement::new().id("card").padding(Padding::all(5)) => {
+                    UiElement::new().text(&bitmap, note, 20, 1.0)
+                }
+                UiElement::new().id(second_id).padding(Padding::all(10)) => {
+                    UiElement::new().text(&bitmap, "b", 20, 1.0)
+                }
+            }
+        });
+    };
+
+    let frames = [("a", true), ("a", true), ("a longer note", true), ("a longer note", false), ("a longer note", false)];
+    let expected_caching = [false, false, false, false, true];
+
+    let mut ui = CatplushContext::new();
+    for ((note, duplicate), cached) in frames.into_iter().zip(expected_caching) {
+        let mut fresh = CatplushContext::begin_layout((800, 600), ChildLayoutDirection::LeftToRight);
+        build(&mut fresh, note, duplicate);
+        let expected = layout(&fresh.end_layout());
+
+        ui.reset((800, 600), ChildLayoutDirection::LeftToRight);
+        build(&mut ui, note, duplicate);
+        ui.solve();
+
+        assert_eq!(ui.is_layout_cached(CARD), cached);
+        assert_eq!(layout(&ui.render_commands().collect::<Vec<_>>()), expected);
+    }
+}
+
+#[test]
+fn deeply_nested_ids_are_cached() {
+    // Every cached node only saves the nodes that aren't cached themselves, so this stays linear
+    const DEPTH: u32 = 20_000;
+    let build = |ui: &mut CatplushContext| {
+        ui.reset((800, 600), ChildLayoutDirection::LeftToRight);
+        for depth in 0..DEPTH {
+            ui.open_element(UiElement::new().id_indexed("nested", depth).padding(Padding::all(1)));
+        }
+        ui.open_element(UiElement::new().sizing(SizingMode::Fixed(10), SizingMode::Fixed(10)));
+        ui.close_element();
+        for _ in 0..DEPTH {
+            ui.close_element();
+        }
+        ui.solve();
+    };
+
+    let mut ui = CatplushContext::new();
+    build(&mut ui);
+    let expected = layout(&ui.render_commands().collect::<Vec<_>>());
+
+    build(&mut ui);
+    assert!(ui.is_layout_cached(ElementId::indexed("nested", 0)));
+    assert!(ui.is_layout_cached(ElementId::indexed("nested", DEPTH - 1)));
+    assert_eq!(layout(&ui.render_commands().collect::<Vec<_>>()), expected);
+}
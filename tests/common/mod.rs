@@ -0,0 +1,15 @@
+//! Helpers shared between the test files.
+
+use catplush::catplush_main::*;
+use glam::Vec2;
+use std::num::NonZeroU32;
+
+/// A 10x20 cell bitmap on a made up texture, with lowercase, uppercase, digits and some punctuation.
+pub fn test_bitmap() -> BitmapConfiguration {
+    BitmapConfiguration::from_texture(
+        CatplushTextureData::new(NonZeroU32::new(1).unwrap(), 100, 100),
+        Vec2::new(10.0, 20.0),
+        " abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789#:(),.",
+        10
+    )
+}
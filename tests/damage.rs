@@ -0,0 +1,139 @@
+use catplush::catplush;
+use catplush::catplush_main::*;
+use glam::Vec2;
+
+mod common;
+use common::test_bitmap;
+
+fn square(x: f32) -> BoundingBox {
+    BoundingBox { x, y: 0.0, width: 50.0, height: 50.0 }
+}
+
+/// Three 50x50 squares in a row.
+fn frame(tracker: &mut DamageTracker, colors: [ObjectColor; 3]) -> Vec<BoundingBox> {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    for color in colors {
+        catplush!(ui, {
+            UiElement::new().rectangle(color, CornerRadius::all(0.0)).sizing(SizingMode::Fixed(50), SizingMode::Fixed(50))
+        });
+    }
+    tracker.update(ui.end_layout()).to_vec()
+}
+
+#[test]
+fn first_frame_is_damaged_everywhere() {
+    let mut tracker = DamageTracker::new();
+    let damage = frame(&mut tracker, [ObjectColor::RED, ObjectColor::GREEN, ObjectColor::BLUE]);
+    assert_eq!(damage, vec![square(0.0), square(50.0), square(100.0)]);
+}
+
+#[test]
+fn default_trackers_start_fully_damaged() {
+    let mut tracker = DamageTracker::default();
+    let damage = frame(&mut tracker, [ObjectColor::RED, ObjectColor::GREEN, ObjectColor::BLUE]);
+    assert_eq!(damage.len(), 3);
+    assert!(tracker.is_fully_damaged() && !tracker.is_unchanged());
+}
+
+#[test]
+fn unchanged_frames_have_no_damage() {
+    let mut tracker = DamageTracker::new();
+    frame(&mut tracker, [ObjectColor::RED, ObjectColor::GREEN, ObjectColor::BLUE]);
+    frame(&mut tracker, [ObjectColor::RED, ObjectColor::GREEN, ObjectColor::BLUE]);
+    assert!(tracker.is_unchanged());
+    assert_eq!(tracker.damaged_area(), None);
+
+    tracker.invalidate();
+    frame(&mut tracker, [ObjectColor::RED, ObjectColor::GREEN, ObjectColor::BLUE]);
+    assert_eq!(tracker.damage().len(), 3);
+    assert!(tracker.is_fully_damaged());
+}
+
+#[test]
+fn invalidating_damages_empty_windows() {
+    let mut tracker = DamageTracker::new();
+    let empty_frame = |tracker: &mut DamageTracker| {
+        let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+        tracker.update(ui.end_layout());
+    };
+
+    empty_frame(&mut tracker);
+    assert!(tracker.is_fully_damaged() && !tracker.is_unchanged());
+    empty_frame(&mut tracker);
+    assert!(!tracker.is_fully_damaged() && tracker.is_unchanged());
+
+    // Like after the window is resized
+    tracker.invalidate();
+    empty_frame(&mut tracker);
+    assert!(tracker.damage().is_empty());
+    assert!(!tracker.is_unchanged());
+}
+
+#[test]
+fn only_changed_commands_are_damaged() {
+    let mut tracker = DamageTracker::new();
+    frame(&mut tracker, [ObjectColor::RED, ObjectColor::GREEN, ObjectColor::BLUE]);
+
+    let damage = frame(&mut tracker, [ObjectColor::RED, ObjectColor::WHITE, ObjectColor::BLUE]);
+    assert_eq!(damage, vec![square(50.0)]);
+
+    // Swapping the outer two damages both of them but not the middle
+    frame(&mut tracker, [ObjectColor::BLUE, ObjectColor::WHITE, ObjectColor::RED]);
+    assert_eq!(tracker.damaged_area(), Some(BoundingBox { x: 0.0, y: 0.0, width: 150.0, height: 50.0 }));
+    assert_eq!(tracker.damage(), &[square(0.0), square(100.0)]);
+}
+
+#[test]
+fn reordered_overlapping_commands_are_damaged() {
+    let mut tracker = DamageTracker::new();
+    let mut overlapping = |colors: [ObjectColor; 2]| {
+        let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+        catplush!(ui, {
+            UiElement::new().sizing(SizingMode::Fixed(50), SizingMode::Fixed(50)).rectangle(colors[0], CornerRadius::all(0.0)) => {
+                UiElement::new().sizing(SizingMode::Fixed(50), SizingMode::Fixed(50)).rectangle(colors[1], CornerRadius::all(0.0));
+            }
+        });
+        tracker.update(ui.end_layout()).to_vec()
+    };
+
+    overlapping([ObjectColor::RED, ObjectColor::BLUE]);
+    assert_eq!(overlapping([ObjectColor::BLUE, ObjectColor::RED]), vec![square(0.0)]);
+}
+
+#[test]
+fn reordering_is_found_alongside_other_changes() {
+    let mut tracker = DamageTracker::new();
+    let mut overlapping = |colors: [ObjectColor; 3]| {
+        let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+        catplush!(ui, {
+            UiElement::new().sizing(SizingMode::Fixed(50), SizingMode::Fixed(50)).rectangle(colors[0], CornerRadius::all(0.0)) => {
+                UiElement::new().sizing(SizingMode::Fixed(50), SizingMode::Fixed(50)).rectangle(colors[1], CornerRadius::all(0.0));
+            }
+            UiElement::new().sizing(SizingMode::Fixed(50), SizingMode::Fixed(50)).rectangle(colors[2], CornerRadius::all(0.0));
+        });
+        tracker.update(ui.end_layout()).to_vec()
+    };
+
+    overlapping([ObjectColor::RED, ObjectColor::BLUE, ObjectColor::WHITE]);
+    assert_eq!(overlapping([ObjectColor::BLUE, ObjectColor::RED, ObjectColor::GREEN]), vec![square(0.0), square(50.0)]);
+}
+
+#[test]
+fn text_from_a_different_part_of_the_texture_is_damaged() {
+    let mut tracker = DamageTracker::new();
+    let mut text = |bitmap: &BitmapConfiguration| {
+        let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+        catplush!(ui, { UiElement::new().text(bitmap, "abc", 20, 1.0); });
+        tracker.update(ui.end_layout()).len()
+    };
+
+    let bitmap = test_bitmap();
+    text(&bitmap);
+    assert_eq!(text(&bitmap), 0);
+
+    let moved = BitmapConfiguration { texture_offset: Vec2::new(100.0, 0.0), ..test_bitmap() };
+    assert_eq!(text(&moved), 1);
+
+    let reordered = BitmapConfiguration { character_list: test_bitmap().character_list.chars().rev().collect(), ..test_bitmap() };
+    assert_eq!(text(&reordered), 1);
+}
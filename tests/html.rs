@@ -0,0 +1,57 @@
+use catplush::catplush_main::*;
+use catplush::catplush_html::*;
+use glam::Vec2;
+use std::num::NonZeroU32;
+
+fn html(backend: &mut HtmlBackend, element: UiElement) -> String {
+    let mut ui = CatplushContext::begin_layout((100, 100), ChildLayoutDirection::LeftToRight);
+    ui.open_element(element);
+    ui.close_element();
+    backend.render(ui.end_layout());
+    backend.to_html()
+}
+
+#[test]
+fn rectangles_are_positioned_divs() {
+    let rectangle = UiElement::new()
+        .rectangle(ObjectColor::RED, CornerRadius::all(2.5))
+        .border(ObjectColor::BLACK, BorderWidth::all(1))
+        .sizing(SizingMode::Fixed(20), SizingMode::Fixed(10));
+    let document = html(&mut HtmlBackend::new(100, 100), rectangle);
+
+    assert!(document.contains(concat!(
+        r#"<div style="position: absolute; left: 0px; top: 0px; width: 20px; height: 10px; box-sizing: border-box; "#,
+        r#"background: rgba(255, 0, 0, 1); border-radius: 2.5px 2.5px 2.5px 2.5px; background-clip: padding-box; "#,
+        r#"border-style: solid; border-color: rgba(0, 0, 0, 1); border-width: 1px 1px 1px 1px;"></div>"#
+    )), "{document}");
+}
+
+#[test]
+fn text_is_escaped() {
+    let bitmap = BitmapConfiguration::from_texture(CatplushTextureData::new(NonZeroU32::new(1).unwrap(), 100, 100), Vec2::new(10.0, 20.0), " ab<>&\"'", 10);
+    let document = html(&mut HtmlBackend::new(100, 100), UiElement::new().text(&bitmap, "<a & \"b\">'", 20, 1.0));
+    assert!(document.contains(">&lt;a &amp; &quot;b&quot;&gt;&#39;</span>"), "{document}");
+}
+
+#[test]
+fn images_are_embedded_and_cropped() {
+    // 40x20, covering a 20x20 box, so the image is shifted left by a quarter of its width
+    let texture = CatplushTextureData::new(NonZeroU32::new(2).unwrap(), 40, 20);
+    let mut backend = HtmlBackend::new(100, 100);
+    backend.add_image(texture, b"Man");
+    let image = UiElement::new().image(texture, None, None, false).image_fit(ImageFit::Cover).sizing(SizingMode::Fixed(20), SizingMode::Fixed(20));
+
+    let document = html(&mut backend, image);
+    assert!(document.contains(r#"<img src="data:image/png;base64,TWFu" alt="" style="position: absolute; left: -10px; top: 0px; width: 40px; height: 20px; max-width: none;">"#), "{document}");
+}
+
+#[test]
+fn empty_sub_textures_are_skipped() {
+    let texture = CatplushTextureData::new(NonZeroU32::new(2).unwrap(), 40, 20);
+    let mut backend = HtmlBackend::new(100, 100);
+    backend.add_image(texture, b"Man");
+
+    let document = html(&mut backend, UiElement::new().image(texture.sub_texture(0, 0, 0, 20), Some(20), Some(20), true));
+    assert!(!document.contains("<img"), "{document}");
+    assert!(!document.contains("NaN") && !document.contains("inf"), "{document}");
+}
@@ -0,0 +1,629 @@
+//! Layout snapshot tests. Each test builds a tree, runs `end_layout` and compares the bounding boxes
+//! against a golden file in `tests/snapshots`. Run with `CATPLUSH_BLESS=1` to write new snapshots
+//! after an intentional layout change, and check the diff before committing them.
+
+use catplush::catplush;
+use catplush::catplush_main::*;
+use glam::Vec2;
+use std::{env, fmt::Write, fs, path::PathBuf};
+
+mod common;
+use common::test_bitmap;
+
+fn format_commands(render_commands: &[RenderCommand]) -> String {
+    let mut output = String::new();
+
+    for (index, render_command) in render_commands.iter().enumerate() {
+        let kind = match render_command.render_data {
+            RenderData::NoType => "none",
+            RenderData::RectangleData(_) => "rectangle",
+            RenderData::TextData(_) => "text",
+            RenderData::ImageData(_) => "image",
+            RenderData::NineSliceData(_) => "nine_slice",
+            _ => "unknown"
+        };
+        let bounding_box = render_command.bounding_box;
+
+        let _ = write!(
+            output,
+            "{index:>3} {kind:<10} x: {:>7.2} y: {:>7.2} w: {:>7.2} h: {:>7.2}",
+            bounding_box.x, bounding_box.y, bounding_box.width, bounding_box.height
+        );
+        if let RenderData::TextData(text) = &render_command.render_data {
+            let _ = write!(output, " lines: {:?}", text.lines().collect::<Vec<_>>());
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+fn assert_snapshot(name: &str, render_commands: &[RenderCommand]) {
+    let actual = format_commands(render_commands);
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots").join(format!("{name}.txt"));
+
+    if env::var_os("CATPLUSH_BLESS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("No snapshot at {}, run with CATPLUSH_BLESS=1 to create it.\n{actual}", path.display()));
+    assert!(expected == actual, "Snapshot {name} doesn't match.\n--- expected\n{expected}\n--- actual\n{actual}");
+}
+
+fn leaf(ui: &mut CatplushContext, element: UiElement) {
+    ui.open_element(element);
+    ui.close_element();
+}
+
+fn fixed(width: i32, height: i32) -> UiElement {
+    UiElement::new().rectangle(ObjectColor::WHITE, CornerRadius::default()).sizing(SizingMode::Fixed(width), SizingMode::Fixed(height))
+}
+
+fn grow() -> UiElement {
+    UiElement::new().rectangle(ObjectColor::WHITE, CornerRadius::default()).sizing(SizingMode::Grow, SizingMode::Grow)
+}
+
+#[test]
+fn fixed_children_left_to_right() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    leaf(&mut ui, fixed(100, 50));
+    leaf(&mut ui, fixed(60, 80));
+
+    assert_snapshot("fixed_children_left_to_right", &ui.end_layout());
+}
+
+#[test]
+fn fixed_children_top_to_bottom() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::TopToBottom);
+    leaf(&mut ui, fixed(100, 50));
+    leaf(&mut ui, fixed(60, 80));
+
+    assert_snapshot("fixed_children_top_to_bottom", &ui.end_layout());
+}
+
+#[test]
+fn fit_wraps_children_padding_and_gaps() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(UiElement::new()
+        .padding(Padding::new(5, 10, 15, 20))
+        .child_gap(7));
+        leaf(&mut ui, fixed(30, 40));
+        leaf(&mut ui, fixed(50, 20));
+        leaf(&mut ui, fixed(10, 10));
+    ui.close_element();
+
+    assert_snapshot("fit_wraps_children_padding_and_gaps", &ui.end_layout());
+}
+
+#[test]
+fn fit_top_to_bottom() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(UiElement::new()
+        .layout_direction(ChildLayoutDirection::TopToBottom)
+        .padding(Padding::all(4))
+        .child_gap(6));
+        leaf(&mut ui, fixed(30, 40));
+        leaf(&mut ui, fixed(50, 20));
+    ui.close_element();
+
+    assert_snapshot("fit_top_to_bottom", &ui.end_layout());
+}
+
+#[test]
+fn nested_fit() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(UiElement::new().padding(Padding::all(10)));
+        ui.open_element(UiElement::new().padding(Padding::all(5)).child_gap(5));
+            leaf(&mut ui, fixed(20, 20));
+            leaf(&mut ui, fixed(20, 30));
+        ui.close_element();
+    ui.close_element();
+
+    assert_snapshot("nested_fit", &ui.end_layout());
+}
+
+#[test]
+fn single_grow_fills_remaining_space() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(UiElement::new()
+        .sizing(SizingMode::Grow, SizingMode::Grow)
+        .padding(Padding::all(10))
+        .child_gap(10));
+        leaf(&mut ui, fixed(100, 50));
+        leaf(&mut ui, grow());
+    ui.close_element();
+
+    assert_snapshot("single_grow_fills_remaining_space", &ui.end_layout());
+}
+
+#[test]
+fn multiple_grow_share_space_evenly() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    leaf(&mut ui, grow());
+    leaf(&mut ui, grow());
+    leaf(&mut ui, grow());
+    leaf(&mut ui, fixed(100, 100));
+
+    assert_snapshot("multiple_grow_share_space_evenly", &ui.end_layout());
+}
+
+#[test]
+fn grow_evens_out_different_starting_sizes() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    leaf(&mut ui, grow().limit_width(100, i32::MAX));
+    leaf(&mut ui, grow());
+
+    assert_snapshot("grow_evens_out_different_starting_sizes", &ui.end_layout());
+}
+
+#[test]
+fn grow_respects_max_limit() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    leaf(&mut ui, grow().limit_width(0, 50));
+    leaf(&mut ui, grow());
+
+    assert_snapshot("grow_respects_max_limit", &ui.end_layout());
+}
+
+#[test]
+fn grow_across_the_layout_direction() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::TopToBottom);
+    ui.open_element(UiElement::new()
+        .sizing(SizingMode::Grow, SizingMode::Grow)
+        .layout_direction(ChildLayoutDirection::TopToBottom)
+        .padding(Padding::hv(20, 10)));
+        leaf(&mut ui, grow().sizing(SizingMode::Grow, SizingMode::Fixed(30)));
+        leaf(&mut ui, fixed(50, 30));
+    ui.close_element();
+
+    assert_snapshot("grow_across_the_layout_direction", &ui.end_layout());
+}
+
+#[test]
+fn min_limit_enlarges_fit_element() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(UiElement::new()
+        .limit_width(120, 200)
+        .limit_height(90, 200));
+        leaf(&mut ui, fixed(20, 20));
+    ui.close_element();
+
+    assert_snapshot("min_limit_enlarges_fit_element", &ui.end_layout());
+}
+
+#[test]
+fn alignment_along_layout_direction() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::TopToBottom);
+    for x_align in [ChildXAlignment::Left, ChildXAlignment::Center, ChildXAlignment::Right] {
+        ui.open_element(UiElement::new()
+            .sizing(SizingMode::Grow, SizingMode::Fixed(50))
+            .alignment(x_align, ChildYAlignment::Top)
+            .padding(Padding::all(5))
+            .child_gap(10));
+            leaf(&mut ui, fixed(40, 20));
+            leaf(&mut ui, fixed(60, 20));
+        ui.close_element();
+    }
+
+    assert_snapshot("alignment_along_layout_direction", &ui.end_layout());
+}
+
+#[test]
+fn alignment_across_layout_direction() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    for y_align in [ChildYAlignment::Top, ChildYAlignment::Center, ChildYAlignment::Bottom] {
+        ui.open_element(UiElement::new()
+            .sizing(SizingMode::Fixed(100), SizingMode::Grow)
+            .alignment(ChildXAlignment::Left, y_align)
+            .padding(Padding::all(5)));
+            leaf(&mut ui, fixed(20, 40));
+            leaf(&mut ui, fixed(20, 80));
+        ui.close_element();
+    }
+
+    assert_snapshot("alignment_across_layout_direction", &ui.end_layout());
+}
+
+#[test]
+fn centered_in_both_directions() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::TopToBottom);
+    ui.open_element(UiElement::new()
+        .sizing(SizingMode::Grow, SizingMode::Grow)
+        .layout_direction(ChildLayoutDirection::TopToBottom)
+        .alignment(ChildXAlignment::Center, ChildYAlignment::Center)
+        .child_gap(10));
+        leaf(&mut ui, fixed(100, 40));
+        leaf(&mut ui, fixed(50, 40));
+    ui.close_element();
+
+    assert_snapshot("centered_in_both_directions", &ui.end_layout());
+}
+
+#[test]
+fn text_is_sized_from_the_bitmap() {
+    let bitmap = test_bitmap();
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::TopToBottom);
+    leaf(&mut ui, UiElement::new().text(&bitmap, "hello", 20, 1.0));
+    leaf(&mut ui, UiElement::new().text(&bitmap, "half size\nand two lines", 10, 1.5));
+
+    assert_snapshot("text_is_sized_from_the_bitmap", &ui.end_layout());
+}
+
+#[test]
+fn demo_like_screen() {
+    let bitmap = test_bitmap();
+    let mut ui = CatplushContext::begin_layout((600, 400), ChildLayoutDirection::TopToBottom);
+    ui.open_element(UiElement::new()
+        .sizing(SizingMode::Grow, SizingMode::Grow)
+        .layout_direction(ChildLayoutDirection::TopToBottom)
+        .padding(Padding::all(10))
+        .child_gap(10));
+
+        ui.open_element(UiElement::new()
+            .rectangle(ObjectColor::BLUE, CornerRadius::all(5.0))
+            .sizing(SizingMode::Grow, SizingMode::Fixed(40))
+            .padding(Padding::all(5))
+            .child_gap(10));
+            ui.open_element(fixed(100, 0).sizing(SizingMode::Fixed(100), SizingMode::Grow).alignment(ChildXAlignment::Center, ChildYAlignment::Center));
+                leaf(&mut ui, UiElement::new().text(&bitmap, "file", 10, 1.0));
+            ui.close_element();
+            leaf(&mut ui, UiElement::new().sizing(SizingMode::Grow, SizingMode::Grow));
+            leaf(&mut ui, fixed(75, 0).sizing(SizingMode::Fixed(75), SizingMode::Grow));
+        ui.close_element();
+
+        ui.open_element(UiElement::new()
+            .sizing(SizingMode::Grow, SizingMode::Grow)
+            .child_gap(10));
+            ui.open_element(UiElement::new()
+                .sizing(SizingMode::Fixed(150), SizingMode::Grow)
+                .layout_direction(ChildLayoutDirection::TopToBottom)
+                .child_gap(5));
+                for _ in 0..3 {
+                    leaf(&mut ui, grow().sizing(SizingMode::Grow, SizingMode::Fixed(30)));
+                }
+            ui.close_element();
+            leaf(&mut ui, grow());
+        ui.close_element();
+
+    ui.close_element();
+
+    assert_snapshot("demo_like_screen", &ui.end_layout());
+}
+
+#[test]
+fn dump_matches_render_commands() {
+    let bitmap = test_bitmap();
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(grow().padding(Padding::new(10, 20, 30, 40)).limit_width(50, 300));
+        leaf(&mut ui, fixed(100, 50));
+        leaf(&mut ui, UiElement::new().text(&bitmap, "dump", 20, 1.0));
+    ui.close_element();
+
+    let (render_commands, dump) = ui.end_layout_with_dump();
+    let padding = dump.nodes[1].padding;
+    assert_eq!((padding.left, padding.right, padding.top, padding.bottom), (10, 20, 30, 40));
+    assert_eq!((dump.nodes[1].width_limit.min, dump.nodes[1].width_limit.max), (50, 300));
+    assert_eq!(dump.nodes[1].height_limit, SizeLimit::default());
+    assert_eq!(dump.nodes.len(), render_commands.len() + 1);
+    assert_eq!(dump.nodes[0].children, vec![1]);
+    assert_eq!(dump.nodes[1].children, vec![2, 3]);
+    assert_eq!(dump.nodes[3].parent, Some(1));
+    assert!(matches!(&dump.nodes[3].object, ObjectDump::Text { text, .. } if text == "dump"));
+
+    for (node, render_command) in dump.nodes[1..].iter().zip(&render_commands) {
+        let bounding_box = render_command.bounding_box;
+        assert_eq!((node.final_pos_x, node.final_pos_y), (bounding_box.x, bounding_box.y));
+        assert_eq!((node.final_size_x, node.final_size_y), (bounding_box.width, bounding_box.height));
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn dump_json_round_trip() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    leaf(&mut ui, fixed(100, 50).padding(Padding::new(1, 2, 3, 4)));
+    let (_, dump) = ui.end_layout_with_dump();
+
+    assert_eq!(LayoutDump::from_json(&dump.to_json().unwrap()).unwrap(), dump);
+}
+
+#[cfg(feature = "ron")]
+#[test]
+fn dump_ron_round_trip() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    leaf(&mut ui, grow().limit_width(10, 200).alignment(ChildXAlignment::Center, ChildYAlignment::Bottom));
+    let (_, dump) = ui.end_layout_with_dump();
+
+    assert_eq!(LayoutDump::from_ron(&dump.to_ron().unwrap()).unwrap(), dump);
+}
+
+#[test]
+fn debug_view_outlines_and_hover_panel() {
+    let bitmap = test_bitmap();
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(grow().padding(Padding::all(10)).child_gap(10));
+        leaf(&mut ui, fixed(100, 50));
+        leaf(&mut ui, fixed(100, 50));
+    ui.close_element();
+    ui.debug_view(&bitmap, Some((50.0, 30.0)));
+
+    let render_commands = ui.end_layout();
+    let debug_commands: Vec<&RenderCommand> = render_commands.iter().filter(|command| command.id == DEBUG_VIEW_ID).collect();
+    assert_eq!(render_commands.len() - debug_commands.len(), 3);
+
+    // Four padding strips, one gap, three outlines, the hover outline, the panel and its text
+    assert_eq!(debug_commands.len(), 11);
+    let gap = debug_commands[4].bounding_box;
+    assert_eq!((gap.x, gap.y, gap.width, gap.height), (110.0, 10.0, 10.0, 280.0));
+
+    let hovered = debug_commands[8].bounding_box;
+    assert_eq!((hovered.x, hovered.y, hovered.width, hovered.height), (10.0, 10.0, 100.0, 50.0));
+    let RenderData::TextData(text) = &debug_commands[10].render_data else {
+        panic!("the hover panel should end with its text");
+    };
+    assert_eq!(text.lines().collect::<Vec<_>>(), vec![
+        "#2 Rectangle",
+        "width: Fixed(100) (min 0)",
+        "height: Fixed(50) (min 0)",
+        "size: 100 x 50",
+        "position: 10, 10",
+        "padding: 0 0 0 0",
+        "gap: 0 LeftToRight"
+    ]);
+}
+
+#[test]
+fn scoped_elements_match_open_and_close() {
+    let mut manual = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    manual.open_element(grow().padding(Padding::all(10)).child_gap(5));
+        leaf(&mut manual, fixed(50, 50));
+        manual.open_element(grow().padding(Padding::all(5)));
+            leaf(&mut manual, fixed(20, 20));
+        manual.close_element();
+    manual.close_element();
+    let expected = format_commands(&manual.end_layout());
+
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    let clicked = ui.element(grow().padding(Padding::all(10)).child_gap(5), |ui| {
+        ui.element(fixed(50, 50), |_| {});
+
+        let mut ui = ui.open_scope(grow().padding(Padding::all(5)));
+        ui.element(fixed(20, 20), |_| {});
+        true
+    });
+
+    assert!(clicked);
+    assert_eq!(ui.validate(), vec![]);
+    assert_eq!(format_commands(&ui.end_layout()), expected);
+}
+
+#[test]
+fn macro_matches_open_and_close() {
+    let mut manual = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::TopToBottom);
+    manual.open_element(grow().padding(Padding::all(10)).child_gap(5));
+        leaf(&mut manual, fixed(50, 50));
+        manual.open_element(grow().padding(Padding::all(5)));
+            leaf(&mut manual, fixed(20, 20));
+            leaf(&mut manual, fixed(30, 20));
+        manual.close_element();
+        leaf(&mut manual, fixed(60, 10));
+    manual.close_element();
+    let expected = format_commands(&manual.end_layout());
+
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::TopToBottom);
+    catplush!(ui, {
+        grow().padding(Padding::all(10)).child_gap(5) => {
+            fixed(50, 50);
+            grow().padding(Padding::all(5)) => {
+                @{
+                    for width in [20, 30] {
+                        catplush!(ui, { fixed(width, 20) });
+                    }
+                }
+            }
+            fixed(60, 10)
+        }
+    });
+
+    assert_eq!(ui.validate(), vec![]);
+    assert_eq!(format_commands(&ui.end_layout()), expected);
+}
+
+#[test]
+fn styles_match_builder_calls() {
+    let button = Style::new()
+        .rectangle(ObjectColor(51, 136, 175, 255), CornerRadius::all(10.0))
+        .alignment(ChildXAlignment::Center, ChildYAlignment::Center)
+        .font(&test_bitmap(), 20, 1.0);
+    let highlighted = Style::new().border(ObjectColor::WHITE, BorderWidth::all(2)).padding(Padding::all(5)).inherit(&button);
+
+    let mut manual = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    manual.open_element(
+        UiElement::new()
+            .rectangle(ObjectColor(51, 136, 175, 255), CornerRadius::all(10.0))
+            .border(ObjectColor::WHITE, BorderWidth::all(2))
+            .padding(Padding::all(5))
+            .alignment(ChildXAlignment::Center, ChildYAlignment::Center)
+            .sizing(SizingMode::Fixed(200), SizingMode::Fixed(100))
+    );
+        leaf(&mut manual, UiElement::new().text(&test_bitmap(), "File", 20, 1.0));
+    manual.close_element();
+    let expected = format_commands(&manual.end_layout());
+
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    catplush!(ui, {
+        UiElement::new().style(&highlighted).sizing(SizingMode::Fixed(200), SizingMode::Fixed(100)) => {
+            UiElement::new().styled_text(&highlighted, "File")
+        }
+    });
+
+    assert_eq!(format_commands(&ui.end_layout()), expected);
+}
+
+#[test]
+fn styled_text_without_a_font_is_left_out() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    leaf(&mut ui, UiElement::new().styled_text(&Style::new(), "File").sizing(SizingMode::Fixed(20), SizingMode::Fixed(10)));
+
+    let render_commands = ui.end_layout();
+    assert_eq!(render_commands.len(), 1);
+    assert!(!matches!(render_commands[0].render_data, RenderData::TextData(_)));
+}
+
+#[test]
+fn styled_text_shares_the_style_bitmap() {
+    let style = Style::new().font(&test_bitmap(), 20, 1.0);
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    leaf(&mut ui, UiElement::new().styled_text(&style, "File"));
+    leaf(&mut ui, UiElement::new().styled_text(&style, "Edit"));
+
+    let style_bitmap: &BitmapConfiguration = &style.text.as_ref().unwrap().bitmap;
+    for command in ui.end_layout() {
+        let RenderData::TextData(text) = command.render_data else { panic!("expected text") };
+        assert!(std::ptr::eq(text.bitmap, style_bitmap));
+    }
+}
+
+#[test]
+fn shared_text_borrows_the_shared_bitmap() {
+    let bitmap = std::sync::Arc::new(test_bitmap());
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    leaf(&mut ui, UiElement::new().shared_text(&bitmap, "File", 20, 1.0));
+    leaf(&mut ui, UiElement::new().shared_text(&bitmap, "Edit", 20, 1.0));
+
+    for command in ui.end_layout() {
+        let RenderData::TextData(text) = command.render_data else { panic!("expected text") };
+        assert!(std::ptr::eq(text.bitmap, &*bitmap));
+    }
+}
+
+#[test]
+fn themes_are_switched_on_the_context() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    assert_eq!(ui.theme().background, Theme::dark().background);
+
+    let theme = Theme::light().with_style("warning", Style::new().rectangle(ObjectColor(200, 50, 50, 255), CornerRadius::all(4.0)));
+    ui.set_theme(&theme);
+    assert_eq!(ui.theme().background, Theme::light().background);
+    assert_eq!(ui.theme().style("warning").color, Some(ObjectColor(200, 50, 50, 255)));
+
+    // Presets share their layout settings and only differ in color
+    let (dark_theme, light_theme) = (Theme::dark(), Theme::light());
+    let (dark, light) = (dark_theme.style("highlighted_button"), light_theme.style("highlighted_button"));
+    assert_eq!(dark.alignment, light.alignment);
+    assert_ne!(dark.color, light.color);
+    assert!(dark.border.is_some());
+
+    let themed = ui.theme().clone().with_font(&test_bitmap(), 20, 1.0);
+    assert!(themed.style("panel").text.is_some());
+    assert!(ui.theme().style("missing").color.is_none());
+}
+
+#[test]
+fn reset_context_matches_a_new_one() {
+    let build = |ui: &mut CatplushContext| {
+        catplush!(ui, {
+            grow().padding(Padding::all(10)).child_gap(5) => {
+                fixed(50, 50);
+                UiElement::new().text(&test_bitmap(), "some text", 20, 1.0);
+            }
+        });
+    };
+
+    let mut fresh = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    build(&mut fresh);
+    let expected = format_commands(&fresh.end_layout());
+
+    let mut ui = CatplushContext::new();
+    for _ in 0..3 {
+        ui.reset((400, 300), ChildLayoutDirection::LeftToRight);
+        build(&mut ui);
+        assert_eq!(format_commands(&ui.end_layout()), expected);
+    }
+}
+
+#[test]
+fn font_text_matches_bitmap_text() {
+    let text = "Gain 30 Block.\nExhaust.";
+
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::TopToBottom);
+    leaf(&mut ui, UiElement::new().text(&test_bitmap(), text, 20, 1.2));
+    let expected = ui.end_layout().iter().map(|command| {
+        let RenderData::TextData(text) = &command.render_data else { panic!("expected text") };
+        (format_commands(std::slice::from_ref(command)), text.glyphs(Vec2::ZERO).map(|glyph| (glyph.uv_pos, glyph.draw_box.x)).collect::<Vec<_>>())
+    }).collect::<Vec<_>>();
+
+    let mut ui = CatplushContext::new();
+    let font = ui.add_font(&test_bitmap());
+    ui.reset((400, 300), ChildLayoutDirection::TopToBottom);
+    leaf(&mut ui, UiElement::new().font_text(font, text, 20, 1.2));
+    let actual = ui.end_layout().iter().map(|command| {
+        let RenderData::TextData(text) = &command.render_data else { panic!("expected text") };
+        assert!(text.font.is_some());
+        (format_commands(std::slice::from_ref(command)), text.glyphs(Vec2::ZERO).map(|glyph| (glyph.uv_pos, glyph.draw_box.x)).collect::<Vec<_>>())
+    }).collect::<Vec<_>>();
+
+    assert_eq!(actual, expected);
+    assert_eq!(ui.font(font).unwrap().glyph_uv('#'), test_bitmap().glyph_uv('#'));
+    assert_eq!(ui.font(font).unwrap().glyph_uv('!'), None);
+}
+
+#[test]
+fn deep_trees_dont_overflow_the_stack() {
+    const DEPTH: usize = 100_000;
+
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    for _ in 0..DEPTH {
+        ui.open_element(UiElement::new().rectangle(ObjectColor::RED, CornerRadius::all(0.0)).padding(Padding::all(1)));
+    }
+    leaf(&mut ui, fixed(10, 10).rectangle(ObjectColor::BLUE, CornerRadius::all(0.0)));
+    for _ in 0..DEPTH {
+        ui.close_element();
+    }
+
+    let render_commands = ui.end_layout();
+    let outermost = render_commands.first().unwrap().bounding_box;
+    let innermost = render_commands.last().unwrap().bounding_box;
+    assert_eq!((outermost.width, outermost.height), (10.0 + 2.0 * DEPTH as f32, 10.0 + 2.0 * DEPTH as f32));
+    assert_eq!((innermost.x, innermost.y), (DEPTH as f32, DEPTH as f32));
+}
+
+#[test]
+fn image_fits() {
+    // 40x20 in a 100x100 box
+    let image = |fit: ImageFit, size: i32| {
+        let texture = CatplushTextureData::new(std::num::NonZeroU32::new(2).unwrap(), 40, 20);
+        let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+        leaf(&mut ui, UiElement::new().image(texture, None, None, false).image_fit(fit).sizing(SizingMode::Fixed(size), SizingMode::Fixed(size)));
+        let RenderData::ImageData(data) = &ui.end_layout()[0].render_data else { panic!("expected an image") };
+        let draw_box = data.draw_box;
+        ((draw_box.x, draw_box.y, draw_box.width, draw_box.height), data.uv_pos, data.uv_size)
+    };
+
+    assert_eq!(image(ImageFit::Fill, 100), ((0.0, 0.0, 100.0, 100.0), Vec2::ZERO, Vec2::ONE));
+    assert_eq!(image(ImageFit::Contain, 100), ((0.0, 25.0, 100.0, 50.0), Vec2::ZERO, Vec2::ONE));
+    assert_eq!(image(ImageFit::Cover, 100), ((0.0, 0.0, 100.0, 100.0), Vec2::new(0.25, 0.0), Vec2::new(0.5, 1.0)));
+    assert_eq!(image(ImageFit::None, 100), ((30.0, 40.0, 40.0, 20.0), Vec2::ZERO, Vec2::ONE));
+
+    // An empty box draws nothing instead of making up uvs
+    for fit in [ImageFit::Fill, ImageFit::Contain, ImageFit::Cover, ImageFit::None] {
+        assert_eq!(image(fit, 0), ((0.0, 0.0, 0.0, 0.0), Vec2::ZERO, Vec2::ONE));
+    }
+}
+
+#[test]
+fn line_spacing_scales_with_font_size() {
+    // 40px text on a 20px tall bitmap, so lines are 40 * 1.5 apart rather than 20 * 1.5
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    leaf(&mut ui, UiElement::new().text(&test_bitmap(), "ab\ncd", 40, 1.5));
+    let render_commands = ui.end_layout();
+    let RenderData::TextData(text) = &render_commands[0].render_data else { panic!("expected text") };
+
+    let line_tops: Vec<f32> = text.glyphs(Vec2::ZERO).map(|glyph| glyph.draw_box.y).collect();
+    assert_eq!(line_tops, vec![1.0, 1.0, 61.0, 61.0]);
+    // Same as the height the layout gave it
+    assert_eq!(render_commands[0].bounding_box.height, 2.0 * 60.0);
+}
@@ -0,0 +1,138 @@
+use catplush::catplush_main::*;
+use catplush::catplush_markup::*;
+use std::{fs, num::NonZeroU32, time::{Duration, SystemTime}};
+
+mod common;
+use common::test_bitmap;
+
+fn resources() -> MarkupResources {
+    MarkupResources::new()
+        .font("test", &test_bitmap())
+        .image("icon", CatplushTextureData::new(NonZeroU32::new(2).unwrap(), 32, 16))
+}
+
+fn boxes(render_commands: &[RenderCommand]) -> Vec<(f32, f32, f32, f32)> {
+    render_commands.iter().map(|command| {
+        let bounding_box = command.bounding_box;
+        (bounding_box.x, bounding_box.y, bounding_box.width, bounding_box.height)
+    }).collect()
+}
+
+fn markup_error(source: &str) -> (usize, usize, String) {
+    match Markup::parse(source, &resources()) {
+        Err(CatplushError::Markup { line, column, message }) => (line, column, message),
+        Err(error) => panic!("expected a markup error, got {error}"),
+        Ok(_) => panic!("expected a markup error")
+    }
+}
+
+#[test]
+fn markup_matches_builder_calls() {
+    let source = r#"
+        // A sidebar with a header and an image
+        element(
+            sizing: (fixed(200), grow),
+            direction: top_to_bottom,
+            padding: (10, 5),
+            gap: 10,
+            align: (center, top),
+            color: #11242eff,
+            radius: 15,
+            border: (#eebe2b, 3),
+            children: [
+                text("cards\nand more", size: 10, line_height: 1.5),
+                image("icon", width: 64, fit: contain),
+                element(sizing: (grow, 20), limit_width: (0, 50)),
+            ],
+        )
+    "#;
+    let markup = Markup::parse(source, &resources()).unwrap();
+
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    markup.build(&mut ui);
+    assert_eq!(ui.validate(), vec![]);
+    let from_markup = ui.end_layout();
+
+    let bitmap = test_bitmap();
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.element(UiElement::new()
+        .sizing(SizingMode::Fixed(200), SizingMode::Grow)
+        .layout_direction(ChildLayoutDirection::TopToBottom)
+        .padding(Padding::hv(10, 5))
+        .child_gap(10)
+        .alignment(ChildXAlignment::Center, ChildYAlignment::Top)
+        .border(ObjectColor(0xee, 0xbe, 0x2b, 255), BorderWidth::all(3))
+        .rectangle(ObjectColor(0x11, 0x24, 0x2e, 255), CornerRadius::all(15.0)), |ui| {
+        ui.element(UiElement::new().text(&bitmap, "cards\nand more", 10, 1.5), |_| {});
+        ui.element(UiElement::new()
+            .image(CatplushTextureData::new(NonZeroU32::new(2).unwrap(), 32, 16), Some(64), None, false)
+            .image_fit(ImageFit::Contain), |_| {});
+        ui.element(UiElement::new().sizing(SizingMode::Grow, SizingMode::Fixed(20)).limit_width(0, 50), |_| {});
+    });
+    let from_builder = ui.end_layout();
+
+    assert_eq!(boxes(&from_markup), boxes(&from_builder));
+    assert!(matches!(from_markup[0].render_data, RenderData::RectangleData(RectangleRenderData { border_width: BorderWidth { top: 3, .. }, .. })));
+    assert!(matches!(&from_markup[1].render_data, RenderData::TextData(text) if text.lines().eq(["cards", "and more"])));
+}
+
+#[test]
+fn mistakes_point_at_the_line_and_column() {
+    assert_eq!(markup_error("element(\n    colour: #ffffff\n)"), (2, 5, "`element` doesn't have a `colour` property".to_string()));
+    assert_eq!(markup_error("element(gap: 5"), (1, 15, "expected `,`".to_string()));
+    assert_eq!(markup_error("text(\"hi!\")").1, 6);
+    assert_eq!(markup_error("image(\"missing\")").2, "no image with this name was added to the resources");
+    assert_eq!(markup_error("text(\"hi\", children: [])").2, "`text` elements can't have children");
+    assert_eq!(markup_error("element(color: #12345)").2, "colors have 6 or 8 hex digits");
+    assert_eq!(markup_error("text(\"hi\", font: \"test\", size: 0)").2, "font sizes have to be bigger than 0");
+    assert_eq!(markup_error("text(\"hi\", font: \"test\", size: -20)").2, "font sizes have to be bigger than 0");
+}
+
+#[test]
+fn file_reloads_when_it_changes() {
+    let path = std::env::temp_dir().join(format!("catplush_markup_test_{}.txt", std::process::id()));
+    fs::write(&path, "element(sizing: (fixed(10), fixed(10)))").unwrap();
+    let resources = resources();
+
+    let mut file = MarkupFile::load(&path, &resources).unwrap();
+    assert!(!file.reload_if_changed(&resources).unwrap());
+
+    // Pushed forward by hand since some file systems only store the time to the second
+    let write_later = |contents: &str, seconds: u64| {
+        fs::write(&path, contents).unwrap();
+        fs::File::options().write(true).open(&path).unwrap().set_modified(SystemTime::now() + Duration::from_secs(seconds)).unwrap();
+    };
+
+    write_later("element(sizing: (fixed(20), fixed(10)))", 10);
+    assert!(file.reload_if_changed(&resources).unwrap());
+
+    // A broken save keeps the last good version
+    write_later("element(sizing: (fixed(30), ", 20);
+    assert!(file.reload_if_changed(&resources).is_err());
+    assert!(!file.reload_if_changed(&resources).unwrap());
+
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    file.build(&mut ui);
+    assert_eq!(boxes(&ui.end_layout()), vec![(0.0, 0.0, 20.0, 10.0)]);
+
+    fs::remove_file(&path).unwrap();
+    let error = file.reload_if_changed(&resources).unwrap_err();
+    assert!(matches!(error, CatplushError::Io(_)));
+    assert_eq!(error.to_string(), "failed to read file");
+}
+
+#[test]
+fn deep_markup_is_an_error() {
+    let nested = |depth: usize| "element(children: [".repeat(depth) + "element()" + &"])".repeat(depth);
+
+    // Every element inside another one is two levels deeper, one for the element and one for the list
+    let markup = Markup::parse(&nested(49), &resources()).unwrap();
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    markup.build(&mut ui);
+    assert_eq!(ui.validate(), vec![]);
+    assert_eq!(ui.end_layout().len(), 50);
+
+    let (line, column, message) = markup_error(&nested(20_000));
+    assert_eq!((line, column), (1, 50 * 19 + 1));
+    assert_eq!(message, "brackets can't be nested more than 100 deep");
+}
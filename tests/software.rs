@@ -0,0 +1,105 @@
+use catplush::catplush_main::*;
+use catplush::catplush_software::SoftwareBackend;
+use glam::Vec2;
+
+/// Lays out one element in the top left corner and draws it onto a 40x20 buffer.
+fn draw(backend: &mut SoftwareBackend, element: UiElement) {
+    let mut ui = CatplushContext::begin_layout((40, 20), ChildLayoutDirection::LeftToRight);
+    ui.open_element(element);
+    ui.close_element();
+    backend.render(ui.end_layout());
+}
+
+fn rectangle(color: ObjectColor, radius: f32, width: i32, height: i32) -> UiElement {
+    UiElement::new().rectangle(color, CornerRadius::all(radius)).sizing(SizingMode::Fixed(width), SizingMode::Fixed(height))
+}
+
+#[test]
+fn rectangles_are_filled() {
+    let mut backend = SoftwareBackend::new(40, 20);
+    draw(&mut backend, rectangle(ObjectColor::RED, 0.0, 20, 10));
+
+    assert_eq!(backend.pixel(0, 0), Some(ObjectColor::RED));
+    assert_eq!(backend.pixel(19, 9), Some(ObjectColor::RED));
+    assert_eq!(backend.pixel(20, 9), Some(ObjectColor::TRANSPARENT));
+    assert_eq!(backend.pixel(19, 10), Some(ObjectColor::TRANSPARENT));
+    assert_eq!(backend.pixel(40, 0), None);
+}
+
+#[test]
+fn borders_are_drawn_inside_the_rectangle() {
+    let mut backend = SoftwareBackend::new(40, 20);
+    draw(&mut backend, rectangle(ObjectColor::WHITE, 0.0, 20, 20).border(ObjectColor::BLACK, BorderWidth::all(2)));
+
+    assert_eq!(backend.pixel(0, 10), Some(ObjectColor::BLACK));
+    assert_eq!(backend.pixel(1, 10), Some(ObjectColor::BLACK));
+    assert_eq!(backend.pixel(2, 10), Some(ObjectColor::WHITE));
+    assert_eq!(backend.pixel(18, 10), Some(ObjectColor::BLACK));
+    assert_eq!(backend.pixel(10, 19), Some(ObjectColor::BLACK));
+}
+
+#[test]
+fn corners_are_rounded() {
+    let mut backend = SoftwareBackend::new(40, 20);
+    draw(&mut backend, rectangle(ObjectColor::RED, 5.0, 20, 20));
+
+    for (x, y) in [(0, 0), (19, 0), (0, 19), (19, 19)] {
+        assert_eq!(backend.pixel(x, y).unwrap().3, 0, "corner at {x}, {y} is drawn");
+    }
+    // The middle of each edge is still straight
+    for (x, y) in [(10, 0), (0, 10), (19, 10), (10, 19), (10, 10)] {
+        assert_eq!(backend.pixel(x, y), Some(ObjectColor::RED), "edge at {x}, {y} isn't drawn");
+    }
+    // Partly covered pixels on the curve are blended
+    let alpha = backend.pixel(1, 1).unwrap().3;
+    assert!(alpha > 0 && alpha < 255, "{alpha}");
+}
+
+#[test]
+fn images_are_sampled_from_their_texture() {
+    let mut backend = SoftwareBackend::new(40, 20);
+    // Red, green / blue, white
+    let pixels = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255], [255, 255, 255, 255]].concat();
+    let texture = backend.add_texture(2, 2, pixels).unwrap();
+    draw(&mut backend, UiElement::new().image(texture, Some(20), None, false));
+
+    assert_eq!(backend.pixel(5, 5), Some(ObjectColor::RED));
+    assert_eq!(backend.pixel(15, 5), Some(ObjectColor::GREEN));
+    assert_eq!(backend.pixel(5, 15), Some(ObjectColor::BLUE));
+    assert_eq!(backend.pixel(15, 15), Some(ObjectColor::WHITE));
+    assert_eq!(backend.pixel(25, 5), Some(ObjectColor::TRANSPARENT));
+}
+
+#[test]
+fn glyphs_are_drawn_from_the_bitmap() {
+    let mut backend = SoftwareBackend::new(40, 20);
+    // Two 2x2 cells, a solid one for `a` and an empty one for `b`
+    let mut pixels = vec![0; 4 * 2 * 4];
+    for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+        pixels[(y * 4 + x) * 4..(y * 4 + x) * 4 + 4].copy_from_slice(&[255, 255, 255, 255]);
+    }
+    let bitmap = BitmapConfiguration::from_texture(backend.add_texture(4, 2, pixels).unwrap(), Vec2::new(2.0, 2.0), "ab", 2);
+    // Drawn at twice the size of the cells
+    draw(&mut backend, UiElement::new().text(&bitmap, "aba", 4, 1.0));
+
+    // Lines start one pixel down
+    assert_eq!(backend.pixel(0, 0), Some(ObjectColor::TRANSPARENT));
+    assert_eq!(backend.pixel(0, 1), Some(ObjectColor::WHITE));
+    assert_eq!(backend.pixel(3, 4), Some(ObjectColor::WHITE));
+    assert_eq!(backend.pixel(4, 1), Some(ObjectColor::TRANSPARENT));
+    assert_eq!(backend.pixel(8, 1), Some(ObjectColor::WHITE));
+    assert_eq!(backend.pixel(12, 1), Some(ObjectColor::TRANSPARENT));
+}
+
+#[test]
+fn trait_objects_render_slices() {
+    let mut ui = CatplushContext::begin_layout((40, 20), ChildLayoutDirection::LeftToRight);
+    ui.open_element(rectangle(ObjectColor::RED, 0.0, 20, 10));
+    ui.close_element();
+    let render_commands = ui.end_layout();
+
+    let mut software = SoftwareBackend::new(40, 20);
+    let backend: &mut dyn RenderBackend = &mut software;
+    backend.render_slice(&render_commands);
+    assert_eq!(software.pixel(0, 0), Some(ObjectColor::RED));
+}
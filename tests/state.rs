@@ -0,0 +1,67 @@
+use catplush::catplush;
+use catplush::catplush_main::*;
+
+const DROPDOWN: ElementId = ElementId::new("dropdown");
+
+fn frame(store: &mut StateStore, show_dropdown: bool, toggle: bool) {
+    let mut ui = CatplushContext::begin_layout_with_state((400, 300), ChildLayoutDirection::TopToBottom, store);
+    if show_dropdown {
+        let open = ui.state::<bool>(DROPDOWN);
+        if toggle {
+            *open = !*open;
+        }
+        catplush!(ui, { UiElement::new().id("dropdown") });
+    }
+    ui.end_layout();
+}
+
+#[test]
+fn state_is_kept_between_frames() {
+    let mut store = StateStore::new();
+    frame(&mut store, true, true);
+    frame(&mut store, true, false);
+    assert_eq!(store.get::<bool>(DROPDOWN), Some(&true));
+
+    frame(&mut store, true, true);
+    assert_eq!(store.get::<bool>(DROPDOWN), Some(&false));
+}
+
+#[test]
+fn state_of_missing_elements_is_removed() {
+    let mut store = StateStore::new();
+    frame(&mut store, true, true);
+    assert_eq!(store.len(), 1);
+
+    frame(&mut store, false, false);
+    assert!(store.is_empty());
+}
+
+#[test]
+fn opening_an_element_keeps_its_state() {
+    let mut store = StateStore::new();
+
+    let mut ui = CatplushContext::begin_layout_with_state((400, 300), ChildLayoutDirection::TopToBottom, &mut store);
+    for row in 0..3 {
+        *ui.state::<f32>(ElementId::indexed("row", row)) = row as f32 * 10.0;
+    }
+    ui.end_layout();
+
+    // Only opened, not asked for, so rows 0 and 2 are still seen
+    let mut ui = CatplushContext::begin_layout_with_state((400, 300), ChildLayoutDirection::TopToBottom, &mut store);
+    for row in [0, 2] {
+        catplush!(ui, { UiElement::new().id_indexed("row", row) });
+    }
+    ui.end_layout();
+
+    assert_eq!(store.get::<f32>(ElementId::indexed("row", 0)), Some(&0.0));
+    assert_eq!(store.get::<f32>(ElementId::indexed("row", 1)), None);
+    assert_eq!(store.get::<f32>(ElementId::indexed("row", 2)), Some(&20.0));
+}
+
+#[test]
+fn state_without_a_store_lasts_one_frame() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::TopToBottom);
+    *ui.state::<u32>(DROPDOWN) += 1;
+    assert_eq!(*ui.state::<u32>(DROPDOWN), 1);
+    ui.end_layout();
+}
@@ -0,0 +1,60 @@
+use catplush::catplush_main::*;
+use catplush::catplush_svg::*;
+use glam::Vec2;
+use std::num::NonZeroU32;
+
+fn svg(backend: &mut SvgBackend, element: UiElement) -> String {
+    let mut ui = CatplushContext::begin_layout((100, 100), ChildLayoutDirection::LeftToRight);
+    ui.open_element(element);
+    ui.close_element();
+    backend.render(ui.end_layout());
+    backend.to_svg()
+}
+
+#[test]
+fn rounded_rectangles_become_paths() {
+    let rectangle = UiElement::new().rectangle(ObjectColor::RED, CornerRadius::all(2.0)).sizing(SizingMode::Fixed(20), SizingMode::Fixed(10));
+    let document = svg(&mut SvgBackend::new(100, 100), rectangle);
+    assert!(document.contains(r#"<path d="M2 0 H18 A2 2 0 0 1 20 2 V8 A2 2 0 0 1 18 10 H2 A2 2 0 0 1 0 8 V2 A2 2 0 0 1 2 0 Z" fill="rgb(255,0,0)"/>"#), "{document}");
+
+    // Radii bigger than half of the box are cut down to fit
+    let pill = UiElement::new().rectangle(ObjectColor::RED, CornerRadius::all(50.0)).sizing(SizingMode::Fixed(20), SizingMode::Fixed(10));
+    let document = svg(&mut SvgBackend::new(100, 100), pill);
+    assert!(document.contains("M5 0 H15 A5 5 0 0 1 20 5"), "{document}");
+}
+
+#[test]
+fn images_are_base64_data_uris() {
+    let texture = |id: u32| CatplushTextureData::new(NonZeroU32::new(id).unwrap(), 1, 1);
+    let mut backend = SvgBackend::new(100, 100);
+    backend.add_image(texture(1), b"Man");
+    backend.add_image(texture(2), b"Ma");
+    backend.add_image(texture(3), b"M");
+    // JPEGs are told apart by their first two bytes
+    backend.add_image(texture(4), &[0xff, 0xd8, 0x01]);
+    let document = backend.to_svg();
+
+    assert!(document.contains(r#"href="data:image/png;base64,TWFu""#), "{document}");
+    assert!(document.contains(r#"href="data:image/png;base64,TWE=""#), "{document}");
+    assert!(document.contains(r#"href="data:image/png;base64,TQ==""#), "{document}");
+    assert!(document.contains(r#"href="data:image/jpeg;base64,/9gB""#), "{document}");
+}
+
+#[test]
+fn text_is_escaped() {
+    let bitmap = BitmapConfiguration::from_texture(CatplushTextureData::new(NonZeroU32::new(1).unwrap(), 100, 100), Vec2::new(10.0, 20.0), " ab<>&\"'", 10);
+    let document = svg(&mut SvgBackend::new(100, 100), UiElement::new().text(&bitmap, "<a & \"b\">'", 20, 1.0));
+    assert!(document.contains(">&lt;a &amp; &quot;b&quot;&gt;&#39;</text>"), "{document}");
+}
+
+#[test]
+fn images_crop_with_the_view_box() {
+    // 40x20, covering a 20x20 box, so only the middle half of it shows
+    let texture = CatplushTextureData::new(NonZeroU32::new(2).unwrap(), 40, 20);
+    let mut backend = SvgBackend::new(100, 100);
+    backend.add_image(texture, b"png");
+    let image = UiElement::new().image(texture, None, None, false).image_fit(ImageFit::Cover).sizing(SizingMode::Fixed(20), SizingMode::Fixed(20));
+
+    let document = svg(&mut backend, image);
+    assert!(document.contains(r##"<svg x="0" y="0" width="20" height="20" viewBox="10 0 20 20" preserveAspectRatio="none"><use href="#texture-2"/></svg>"##), "{document}");
+}
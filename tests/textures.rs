@@ -0,0 +1,119 @@
+use catplush::catplush_main::*;
+use glam::Vec2;
+use std::num::NonZeroU32;
+
+fn texture(width: i32, height: i32) -> CatplushTextureData {
+    CatplushTextureData::new(NonZeroU32::new(1).unwrap(), width, height)
+}
+
+#[test]
+fn grid_splits_left_to_right_then_top_to_bottom() {
+    // 3x2 cells of 10x10, with a leftover 5 pixel strip on the right that isn't a frame
+    let sheet = SpriteSheet::grid(texture(35, 20), 10, 10);
+    assert_eq!(sheet.frame_count(), 6);
+
+    let frame = sheet.frame(4).unwrap();
+    assert_eq!((frame.width, frame.height), (10, 10));
+    assert_eq!(frame.uv_pos, Vec2::new(10.0 / 35.0, 0.5));
+    assert_eq!(frame.uv_size, Vec2::new(10.0 / 35.0, 0.5));
+
+    assert!(sheet.frame(6).is_none());
+}
+
+#[test]
+fn grid_of_a_sub_texture_stays_inside_it() {
+    let sub_texture = texture(100, 100).sub_texture(50, 0, 50, 50);
+    let frame = SpriteSheet::grid(sub_texture, 25, 25).frame(3).unwrap();
+    assert_eq!(frame.uv_pos, Vec2::new(0.75, 0.25));
+    assert_eq!(frame.uv_size, Vec2::new(0.25, 0.25));
+}
+
+#[test]
+#[should_panic(expected = "at least 1x1")]
+fn grid_with_empty_cells_panics() {
+    SpriteSheet::grid(texture(32, 32), 0, 16);
+}
+
+#[test]
+fn frames_can_be_named() {
+    let sheet = SpriteSheet::grid(texture(20, 10), 10, 10)
+        .name_frame("right", 1)
+        .with_frame("middle", 5, 0, 10, 10);
+
+    assert_eq!(sheet.frame_count(), 3);
+    assert_eq!(sheet.named("right").unwrap().uv_pos, Vec2::new(0.5, 0.0));
+    assert_eq!(sheet.named("middle").unwrap().uv_pos, Vec2::new(0.25, 0.0));
+    assert!(sheet.named("left").is_none());
+}
+
+/// Builds an atlas out of blank images, numbering the pages from 1.
+#[cfg(feature = "friend")]
+fn atlas(page_size: u32, sizes: &[(u32, u32)]) -> (catplush::catplush_friend::TextureAtlas, Vec<catplush::catplush_friend::AtlasEntry>) {
+    let mut builder = catplush::catplush_friend::TextureAtlasBuilder::new(page_size);
+    let entries = sizes.iter().map(|&(width, height)| builder.add_image(&image::DynamicImage::new_rgba8(width, height))).collect();
+    let mut next_texture = 0;
+    let atlas = builder.build_with(|_| {
+        next_texture += 1;
+        NonZeroU32::new(next_texture).unwrap()
+    });
+    (atlas, entries)
+}
+
+/// The page an entry ended up on and where it is on it in pixels.
+#[cfg(feature = "friend")]
+fn placement(atlas: &catplush::catplush_friend::TextureAtlas, entry: catplush::catplush_friend::AtlasEntry) -> (u32, i32, i32, i32, i32) {
+    let texture = atlas.get(entry);
+    let page = atlas.pages().iter().find(|page| page.texture_id == texture.texture_id).unwrap();
+    let page_size = Vec2::new(page.width as f32, page.height as f32);
+    let position = (texture.uv_pos * page_size).round();
+    let size = (texture.uv_size * page_size).round();
+    assert_eq!((size.x as i32, size.y as i32), (texture.width, texture.height));
+    (texture.texture_id.get(), position.x as i32, position.y as i32, texture.width, texture.height)
+}
+
+#[test]
+#[cfg(feature = "friend")]
+fn atlas_packs_tallest_first_in_rows() {
+    let (atlas, entries) = atlas(64, &[(10, 20), (30, 10), (20, 20)]);
+
+    assert_eq!(atlas.pages().len(), 1);
+    // Sized to what was used, with the 1 pixel of padding between images
+    assert_eq!((atlas.pages()[0].width, atlas.pages()[0].height), (62, 20));
+    assert_eq!(placement(&atlas, entries[0]), (1, 0, 0, 10, 20));
+    assert_eq!(placement(&atlas, entries[2]), (1, 11, 0, 20, 20));
+    assert_eq!(placement(&atlas, entries[1]), (1, 32, 0, 30, 10));
+}
+
+#[test]
+#[cfg(feature = "friend")]
+fn atlas_images_dont_overlap() {
+    let sizes: Vec<(u32, u32)> = (0..40).map(|i| (3 + i * 7 % 29, 2 + i * 11 % 23)).collect();
+    let (atlas, entries) = atlas(64, &sizes);
+    assert!(atlas.pages().len() > 1);
+
+    let placements: Vec<_> = entries.iter().map(|&entry| placement(&atlas, entry)).collect();
+    for (i, &(page, x, y, width, height)) in placements.iter().enumerate() {
+        assert!(x + width <= 64 && y + height <= 64, "image {i} goes off its page");
+
+        for &(other_page, other_x, other_y, other_width, other_height) in &placements[i + 1..] {
+            let overlaps = page == other_page && x < other_x + other_width && other_x < x + width && y < other_y + other_height && other_y < y + height;
+            assert!(!overlaps, "image {i} overlaps another one");
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "friend")]
+fn images_too_big_for_a_page_get_their_own() {
+    let (atlas, entries) = atlas(64, &[(10, 10), (100, 10), (10, 10)]);
+
+    assert_eq!(atlas.pages().len(), 2);
+    let (big_page, ..) = placement(&atlas, entries[1]);
+    assert_eq!(placement(&atlas, entries[1]), (big_page, 0, 0, 100, 10));
+    assert_eq!((atlas.pages()[big_page as usize - 1].width, atlas.pages()[big_page as usize - 1].height), (100, 10));
+
+    // The small ones share the other page
+    let (small_page, ..) = placement(&atlas, entries[0]);
+    assert_ne!(small_page, big_page);
+    assert_eq!(placement(&atlas, entries[2]).0, small_page);
+}
@@ -0,0 +1,68 @@
+use catplush::catplush_main::*;
+use catplush::catplush_tui::*;
+
+mod common;
+use common::test_bitmap;
+
+/// Lays out one element in the top left corner of a 6x3 grid of 10x20 cells.
+fn draw(backend: &mut TerminalBackend, element: UiElement) {
+    let mut ui = CatplushContext::begin_layout(backend.layout_size(), ChildLayoutDirection::LeftToRight);
+    ui.open_element(element);
+    ui.close_element();
+    backend.render(ui.end_layout());
+}
+
+#[test]
+fn borders_use_box_drawing_characters() {
+    let mut backend = TerminalBackend::new(6, 3, 10.0, 20.0);
+    let element = UiElement::new()
+        .rectangle(ObjectColor::TRANSPARENT, CornerRadius::all(4.0))
+        .border(ObjectColor::WHITE, BorderWidth::all(1))
+        .sizing(SizingMode::Fixed(60), SizingMode::Fixed(60));
+    draw(&mut backend, element);
+
+    assert_eq!(backend.to_plain_string(), "╭────╮\n│    │\n╰────╯\n");
+    assert_eq!(backend.cell(0, 0).unwrap().foreground, Some(ObjectColor::WHITE));
+}
+
+#[test]
+fn backgrounds_fill_the_cells_they_cover() {
+    let mut backend = TerminalBackend::new(6, 3, 10.0, 20.0);
+    draw(&mut backend, UiElement::new().rectangle(ObjectColor::RED, CornerRadius::default()).sizing(SizingMode::Fixed(20), SizingMode::Fixed(20)));
+
+    assert_eq!(backend.cell(1, 0).unwrap().background, Some(ObjectColor::RED));
+    assert_eq!(backend.cell(2, 0).unwrap().background, None);
+    assert_eq!(backend.cell(0, 1).unwrap().background, None);
+    assert!(backend.to_ansi_string().starts_with("\x1b[48;2;255;0;0m  \x1b[49m    \x1b[0m\n"));
+}
+
+#[test]
+fn text_takes_one_cell_per_character() {
+    let mut backend = TerminalBackend::new(6, 3, 10.0, 20.0).text_color(ObjectColor::GREEN);
+    draw(&mut backend, UiElement::new().text(&test_bitmap(), "abc", 20, 1.0));
+
+    assert_eq!(backend.to_plain_string(), "abc   \n      \n      \n");
+    assert_eq!(backend.cell(2, 0).unwrap().foreground, Some(ObjectColor::GREEN));
+}
+
+#[test]
+fn cells_outside_the_grid_are_none() {
+    let backend = TerminalBackend::new(6, 3, 10.0, 20.0);
+    assert!(backend.cell(5, 2).is_some());
+    assert!(backend.cell(6, 0).is_none());
+    assert!(backend.cell(0, 3).is_none());
+}
+
+#[test]
+fn empty_grids_print_nothing() {
+    let mut backend = TerminalBackend::new(0, 0, 10.0, 20.0);
+    draw(&mut backend, UiElement::new().rectangle(ObjectColor::RED, CornerRadius::default()).sizing(SizingMode::Fixed(20), SizingMode::Fixed(20)));
+
+    assert_eq!(backend.to_plain_string(), "");
+    assert_eq!(backend.to_ansi_string(), "");
+    assert!(backend.cell(0, 0).is_none());
+
+    // Columns but no rows, and the other way around
+    assert_eq!(TerminalBackend::new(4, 0, 10.0, 20.0).to_plain_string(), "");
+    assert_eq!(TerminalBackend::new(0, 4, 10.0, 20.0).to_ansi_string(), "");
+}
@@ -0,0 +1,125 @@
+use catplush::catplush_main::*;
+
+mod common;
+use common::test_bitmap;
+
+#[test]
+fn balanced_tree_has_no_issues() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(UiElement::new().limit_width(10, 10));
+        ui.open_element(UiElement::new());
+        ui.close_element();
+    ui.close_element();
+
+    assert_eq!(ui.validate(), vec![]);
+}
+
+#[test]
+fn extra_close_points_at_the_call() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(UiElement::new());
+    ui.close_element();
+    ui.close_element(); let line = line!();
+
+    let issues = ui.validate();
+    let [LayoutIssue::ExtraClose { location }] = issues[..] else {
+        panic!("expected one extra close, got {issues:?}");
+    };
+    assert_eq!((location.file(), location.line()), (file!(), line));
+}
+
+#[test]
+fn unclosed_element_is_reported() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(UiElement::new()); let line = line!();
+        ui.open_element(UiElement::new());
+        ui.close_element();
+
+    let issues = ui.validate();
+    let [LayoutIssue::UnclosedElement { element }] = issues[..] else {
+        panic!("expected one unclosed element, got {issues:?}");
+    };
+    assert_eq!(element.index, 1);
+    assert_eq!(element.location.map(|location| location.line()), Some(line));
+    assert!(issues[0].to_string().starts_with("element #1 opened at tests/validation.rs:"));
+}
+
+#[test]
+fn children_inside_text_are_reported() {
+    let bitmap = test_bitmap();
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(UiElement::new().text(&bitmap, "label", 20, 1.0));
+        ui.open_element(UiElement::new());
+        ui.close_element();
+    ui.close_element();
+
+    let issues = ui.validate();
+    let [LayoutIssue::ChildrenOnLeaf { element, child }] = issues[..] else {
+        panic!("expected one child on a leaf, got {issues:?}");
+    };
+    assert_eq!((element.index, child.index), (1, 2));
+}
+
+#[test]
+fn children_inside_nine_slices_are_fine() {
+    let texture = CatplushTextureData::new(std::num::NonZeroU32::new(1).unwrap(), 32, 32);
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(UiElement::new().nine_slice(texture, NineSliceInsets::all(8)).padding(Padding::all(8)));
+        ui.open_element(UiElement::new().text(&test_bitmap(), "label", 20, 1.0));
+        ui.close_element();
+    ui.close_element();
+
+    assert_eq!(ui.validate(), vec![]);
+}
+
+#[test]
+fn contradictory_limits_are_reported() {
+    let mut ui = CatplushContext::begin_layout((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(UiElement::new().limit_width(200, 100).limit_height(5, 50));
+    ui.close_element();
+
+    assert!(matches!(
+        ui.validate()[..],
+        [LayoutIssue::InvalidLimit { dimension: "width", min: 200, max: 100, .. }]
+    ));
+}
+
+#[test]
+fn characters_missing_from_a_font_are_reported() {
+    let mut ui = CatplushContext::new();
+    let font = ui.add_font(&test_bitmap());
+    ui.reset((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(UiElement::new().font_text(font, "fine", 20, 1.0));
+    ui.close_element();
+    ui.open_element(UiElement::new().font_text(font, "not fine!", 20, 1.0).id("shouting"));
+    ui.close_element();
+
+    let issues = ui.validate();
+    let [LayoutIssue::MissingCharacter { element, character: '!' }] = issues[..] else {
+        panic!("expected a missing !, got {issues:?}");
+    };
+    assert_eq!((element.index, element.id), (2, Some("shouting")));
+}
+
+#[test]
+fn fonts_from_other_contexts_are_reported() {
+    let mut other = CatplushContext::new();
+    let foreign_font = other.add_font(&test_bitmap());
+
+    // Both fonts have the same index, so only the context tells them apart
+    let mut ui = CatplushContext::new();
+    ui.add_font(&test_bitmap());
+    ui.reset((400, 300), ChildLayoutDirection::LeftToRight);
+    ui.open_element(UiElement::new().font_text(foreign_font, "lost", 20, 1.0).id("lost"));
+    ui.close_element();
+
+    let issues = ui.validate();
+    let [LayoutIssue::ForeignFont { element }] = issues[..] else {
+        panic!("expected a foreign font, got {issues:?}");
+    };
+    assert_eq!((element.index, element.id), (1, Some("lost")));
+    assert!(ui.font(foreign_font).is_none());
+
+    let render_commands = ui.end_layout();
+    assert!(matches!(render_commands[0].render_data, RenderData::NoType));
+}